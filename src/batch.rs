@@ -0,0 +1,568 @@
+//! Operations on many records at once, chunked to Airtable's limit of 10 records per request, or
+//! fewer with `Base::batch_chunk_size`.
+//! Up to `Base::batch_concurrency` chunks are sent at once, each through the rate limiter.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+use std::thread;
+
+use crate::http::{Request, Response};
+use crate::pagination;
+use crate::{Base, CancellationToken, Error, QueryBuilder, Record, RecordPage, SRecord};
+
+/// The most records Airtable accepts in one create, update or delete request.
+pub const BATCH_SIZE: usize = 10;
+
+/// Reported to the `Base::on_batch_progress` callback after each chunk of a batch operation.
+#[derive(Debug)]
+pub struct BatchProgress<'a> {
+    /// Records in the chunks that have succeeded so far.
+    pub done: usize,
+    pub total: usize,
+    /// Why the chunk just finished failed, if it did. The operation stops once the chunks sent
+    /// alongside a failed one have finished.
+    pub error: Option<&'a anyhow::Error>,
+}
+
+/// Context on the error from a batch operation that failed part way, listing what was written
+/// before it stopped. The underlying error still downcasts as before; get this one with
+/// `err.downcast_ref::<IncompleteBatch>()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncompleteBatch {
+    /// The ids of the records in the chunks that succeeded, including ones sent alongside the
+    /// chunk that failed, so they needn't be the first records of the input.
+    pub written: Vec<String>,
+}
+
+impl fmt::Display for IncompleteBatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "batch stopped after writing {} records",
+            self.written.len()
+        )
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct ProgressHook(pub(crate) Arc<dyn Fn(&BatchProgress<'_>) + Send + Sync>);
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHook")
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct PartialUpdate<'a> {
+    id: &'a str,
+    fields: &'a serde_json::Value,
+}
+
+#[derive(Serialize, Debug)]
+struct BatchRequest<R> {
+    records: Vec<R>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BatchResponse<T> {
+    records: Vec<SRecord<T>>,
+}
+
+// Reads only the ids out of a batch response, skipping over the fields.
+#[derive(Deserialize, Debug)]
+struct ReturnedId {
+    id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReturnedIds {
+    records: Vec<ReturnedId>,
+}
+
+impl<T: Record> Base<T> {
+    /// Deletes the records with the given ids, ten per request, and returns the deleted ids.
+    pub async fn delete_batch<S: AsRef<str>>(&self, ids: &[S]) -> Result<Vec<String>> {
+        let url = self.records_endpoint();
+        let requests = ids.chunks(self.batch_chunk_size).map(|chunk| {
+            chunk.iter().fold(Request::new("DELETE", &url), |req, id| {
+                req.query("records[]", id.as_ref())
+            })
+        });
+
+        // Forgotten up front so an error part way through can't leave deleted records cached.
+        for id in ids {
+            self.forget_cached(id.as_ref());
+        }
+
+        let mut deleted = Vec::with_capacity(ids.len());
+        for response in self.send_all(requests.collect(), ids.len())? {
+            let response: ReturnedIds = response.json()?;
+            deleted.extend(response.records.into_iter().map(|record| record.id));
+        }
+
+        Ok(deleted)
+    }
+
+    /// Deletes every record whose `field` holds the same value as an earlier record, keeping
+    /// the first one seen in the table's default order. Records with the field empty are left
+    /// alone. Returns the ids of the deleted records; with `dry_run` nothing is deleted and the
+    /// ids are the ones that would have been.
+    pub async fn dedupe_by(&self, field: &str, dry_run: bool) -> Result<Vec<String>> {
+        let records = self.scan_fields(&[field])?;
+
+        let mut seen = HashSet::new();
+        let duplicates: Vec<String> = records
+            .into_iter()
+            .filter_map(|record| {
+                let value = record.fields.get(field)?.to_string();
+                if seen.insert(value) {
+                    None
+                } else {
+                    Some(record.id)
+                }
+            })
+            .collect();
+
+        if dry_run {
+            return Ok(duplicates);
+        }
+        self.delete_batch(&duplicates).await
+    }
+
+    /// Deletes every record in the table and returns how many there were. Airtable has no
+    /// truncate, so this pages through the whole table and deletes ten records per request,
+    /// paced by the rate limiter if the base has one.
+    pub async fn truncate(&self) -> Result<usize> {
+        let ids = self.record_ids(&Default::default())?;
+        Ok(self.delete_batch(&ids).await?.len())
+    }
+
+    // Sends the chunk requests of a batch operation over `total` records, `batch_concurrency`
+    // at a time, and returns the responses in the same order. Stops after the first group with
+    // a failed request, returning its first error with the records written so far as context.
+    pub(crate) fn send_all(&self, requests: Vec<Request>, total: usize) -> Result<Vec<Response>> {
+        self.send_until_cancelled(requests, total, None)
+    }
+
+    // Like `send_all`, but stops sending once `cancel` is cancelled. Chunks already in flight
+    // still finish, so the responses always cover whole chunks at the start of `requests`.
+    pub(crate) fn send_until_cancelled(
+        &self,
+        requests: Vec<Request>,
+        total: usize,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<Vec<Response>> {
+        let mut responses = Vec::with_capacity(requests.len());
+        let mut done = 0;
+        let mut sent = 0;
+        let mut requests = requests.into_iter().peekable();
+        let retry_budget = self.batch_retry_budget.map(AtomicUsize::new);
+        let retry_budget = retry_budget.as_ref();
+
+        while requests.peek().is_some() {
+            if cancel.is_some_and(CancellationToken::is_cancelled) {
+                break;
+            }
+            let group: Vec<Request> = requests.by_ref().take(self.batch_concurrency).collect();
+            let results: Vec<Result<Response>> = thread::scope(|scope| {
+                let handles: Vec<_> = group
+                    .into_iter()
+                    .map(|req| {
+                        scope.spawn(move || Ok(self.send_within_budget(req, retry_budget)?.value))
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| {
+                        handle
+                            .join()
+                            .unwrap_or_else(|_| Err(anyhow!("batch request panicked")))
+                    })
+                    .collect()
+            });
+            let mut failed = None;
+            for result in results {
+                // The records this chunk covers, which the last chunk may have fewer of.
+                let chunk = self.batch_chunk_size.min(total.saturating_sub(sent));
+                sent += chunk;
+                if result.is_ok() {
+                    done += chunk;
+                }
+                if let Some(ref progress) = self.batch_progress {
+                    (progress.0)(&BatchProgress {
+                        done,
+                        total,
+                        error: result.as_ref().err(),
+                    });
+                }
+                match result {
+                    Ok(response) => responses.push(response),
+                    Err(err) => {
+                        failed.get_or_insert(err);
+                    }
+                }
+            }
+            if let Some(err) = failed {
+                let written = responses
+                    .iter()
+                    .filter_map(|response| response.json::<ReturnedIds>().ok())
+                    .flat_map(|returned| returned.records.into_iter().map(|record| record.id))
+                    .collect();
+                return Err(err.context(IncompleteBatch { written }));
+            }
+        }
+
+        Ok(responses)
+    }
+
+    // Reads every record's raw fields, narrowed to `fields`.
+    fn scan_fields(
+        &self,
+        fields: &[&str],
+    ) -> Result<Vec<SRecord<serde_json::Map<String, serde_json::Value>>>> {
+        let url = self.records_endpoint();
+
+        pagination::fetch_all(None, |offset: Option<&String>| {
+            let mut req = Request::new("GET", &url);
+            for field in fields {
+                req = req.query("fields[]", field);
+            }
+            if let Some(offset) = offset {
+                req = req.query("offset", offset);
+            }
+            let page: RecordPage<serde_json::Map<String, serde_json::Value>> =
+                self.send(req)?.json()?;
+            Ok(page)
+        })
+    }
+}
+
+impl<T> Base<T>
+where
+    for<'de> T: Deserialize<'de>,
+    T: Record,
+{
+    /// Updates many records, sending only the given fields for each id rather than the whole
+    /// struct so columns changed concurrently by someone else aren't overwritten. Each value must
+    /// be a JSON object of field names to new values. Returns the updated records.
+    pub async fn update_batch_partial(
+        &self,
+        updates: &[(String, serde_json::Value)],
+    ) -> Result<Vec<T>> {
+        if let Some((id, _)) = updates.iter().find(|(_, fields)| !fields.is_object()) {
+            return Err(Error::FieldsNotAnObject { id: id.clone() }.into());
+        }
+
+        let url = self.records_endpoint();
+        let mut requests = vec![];
+        for chunk in updates.chunks(self.batch_chunk_size) {
+            let body = BatchRequest {
+                records: chunk
+                    .iter()
+                    .map(|(id, fields)| PartialUpdate { id, fields })
+                    .collect(),
+            };
+            requests.push(Request::new("PATCH", &url).body(self.write_body(&body)?));
+        }
+        for (id, _) in updates {
+            self.forget_cached(id);
+        }
+
+        let mut updated = Vec::with_capacity(updates.len());
+        for response in self.send_all(requests, updates.len())? {
+            let response: BatchResponse<T> = response.json()?;
+            updated.extend(response.records.into_iter().map(SRecord::into_record));
+        }
+
+        Ok(updated)
+    }
+}
+
+/// What an upsert did: the records it created and the ones that matched an existing record
+/// and were updated instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpsertResult<T> {
+    pub created: Vec<T>,
+    pub updated: Vec<T>,
+}
+
+/// What a cancellable batch operation wrote, e.g. from `Base::create_batch_cancellable`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchOutcome<T> {
+    /// The records written, which are always the first `records.len()` of the input.
+    pub records: Vec<T>,
+    /// Whether the operation stopped early because it was cancelled.
+    pub cancelled: bool,
+}
+
+/// How far a resumable import got, from `Base::upsert_resumable`. It formats as text, e.g.
+/// `"20/25"`, and parses back with `str::parse`, so it can be persisted between runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResumeToken {
+    /// Records at the start of the input that have been written.
+    pub done: usize,
+    /// How many records the import was given, to catch resuming it with different input.
+    pub total: usize,
+}
+
+impl fmt::Display for ResumeToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.done, self.total)
+    }
+}
+
+impl std::str::FromStr for ResumeToken {
+    type Err = anyhow::Error;
+
+    fn from_str(token: &str) -> Result<Self> {
+        let parsed = token
+            .split_once('/')
+            .and_then(|(done, total)| Some((done.parse().ok()?, total.parse().ok()?)));
+        match parsed {
+            Some((done, total)) if done <= total => Ok(ResumeToken { done, total }),
+            _ => Err(anyhow!("invalid resume token {:?}", token)),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PerformUpsert<'a> {
+    fields_to_merge_on: &'a [&'a str],
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct UpsertRequest<'a, R> {
+    perform_upsert: PerformUpsert<'a>,
+    records: Vec<R>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct UpsertResponse<T> {
+    records: Vec<SRecord<T>>,
+    #[serde(default)]
+    created_records: HashSet<String>,
+}
+
+impl<T> Base<T>
+where
+    for<'de> T: Deserialize<'de>,
+    T: Record + Serialize,
+{
+    /// Creates the records, ten per request, and returns them as created, in the same order.
+    /// Retried like `create`.
+    pub async fn create_batch(&self, records: &[T]) -> Result<Vec<T>> {
+        let mut created = Vec::with_capacity(records.len());
+        for response in self.send_all(self.create_requests(records)?, records.len())? {
+            let response: BatchResponse<T> = response.json()?;
+            created.extend(response.records.into_iter().map(SRecord::into_record));
+        }
+
+        Ok(created)
+    }
+
+    /// Like `create_batch`, but stops sending chunks once `cancel` is cancelled, e.g. when the
+    /// user aborts an import. Chunks already sent are waited for, so no chunk is left half
+    /// written and the outcome lists exactly the records that were created.
+    pub async fn create_batch_cancellable(
+        &self,
+        records: &[T],
+        cancel: &CancellationToken,
+    ) -> Result<BatchOutcome<T>> {
+        let requests = self.create_requests(records)?;
+        let chunks = requests.len();
+        let responses = self.send_until_cancelled(requests, records.len(), Some(cancel))?;
+
+        let cancelled = responses.len() < chunks;
+        let mut created = Vec::with_capacity(records.len());
+        for response in responses {
+            let response: BatchResponse<T> = response.json()?;
+            created.extend(response.records.into_iter().map(SRecord::into_record));
+        }
+
+        Ok(BatchOutcome {
+            records: created,
+            cancelled,
+        })
+    }
+
+    /// Like `create_batch`, but returns only the new records' ids, in the same order as
+    /// `records`, without deserializing the fields Airtable sends back. For large imports where
+    /// the ids are only needed to link to the new rows.
+    pub async fn create_batch_ids(&self, records: &[T]) -> Result<Vec<String>> {
+        let mut ids = Vec::with_capacity(records.len());
+        for response in self.send_all(self.create_requests(records)?, records.len())? {
+            let response: ReturnedIds = response.json()?;
+            ids.extend(response.records.into_iter().map(|record| record.id));
+        }
+
+        Ok(ids)
+    }
+
+    /// Like `create_batch`, but takes the records from an iterator, e.g. rows parsed from a file
+    /// as it's read, holding only one chunk of them at a time. Chunks are sent one after
+    /// another, so `batch_concurrency` doesn't apply. Fails at the first chunk that does, by
+    /// which point the chunks before it have been created.
+    pub async fn create_all(&self, records: impl IntoIterator<Item = T>) -> Result<Vec<T>> {
+        let mut records = records.into_iter();
+        let mut created = vec![];
+        loop {
+            let chunk: Vec<T> = records.by_ref().take(self.batch_chunk_size).collect();
+            if chunk.is_empty() {
+                return Ok(created);
+            }
+            created.extend(self.create_batch(&chunk).await?);
+        }
+    }
+
+    fn create_requests(&self, records: &[T]) -> Result<Vec<Request>> {
+        let url = self.records_endpoint();
+        records
+            .chunks(self.batch_chunk_size)
+            .map(|chunk| {
+                let body = BatchRequest {
+                    records: chunk.iter().map(SRecord::from).collect(),
+                };
+                Ok(Request::new("POST", &url).body(self.write_body(&body)?))
+            })
+            .collect()
+    }
+
+    /// Creates or updates each record, matching existing records on `fields_to_merge_on`, ten
+    /// per request. Airtable reports which records it created, which is how the result tells
+    /// new records from updated ones.
+    pub async fn upsert(
+        &self,
+        records: &[T],
+        fields_to_merge_on: &[&str],
+    ) -> Result<UpsertResult<T>> {
+        let url = self.records_endpoint();
+        let mut requests = vec![];
+        for chunk in records.chunks(self.batch_chunk_size) {
+            let body = UpsertRequest {
+                perform_upsert: PerformUpsert { fields_to_merge_on },
+                records: chunk.iter().map(SRecord::from).collect(),
+            };
+            requests.push(Request::new("PATCH", &url).body(self.write_body(&body)?));
+        }
+
+        let mut result = UpsertResult {
+            created: vec![],
+            updated: vec![],
+        };
+        for response in self.send_all(requests, records.len())? {
+            let response: UpsertResponse<T> = response.json()?;
+            for record in response.records {
+                self.forget_cached(&record.id);
+                if response.created_records.contains(&record.id) {
+                    result.created.push(record.into_record());
+                } else {
+                    result.updated.push(record.into_record());
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Upserts `records` like `upsert`, a group of chunks at a time, calling `checkpoint` with a
+    /// token for the progress so far after each group succeeds. If the import fails, call this
+    /// again with the same records and the last token to carry on where it stopped rather than
+    /// starting over. Merging on `fields_to_merge_on`, which should hold a unique value per
+    /// record, is what keeps that safe: a chunk that was written but never confirmed is sent
+    /// again on resume and updates the rows it created instead of duplicating them.
+    ///
+    /// The result only covers the records written by this call.
+    pub async fn upsert_resumable(
+        &self,
+        records: &[T],
+        fields_to_merge_on: &[&str],
+        resume_from: Option<ResumeToken>,
+        mut checkpoint: impl FnMut(ResumeToken),
+    ) -> Result<UpsertResult<T>> {
+        let total = records.len();
+        let start = match resume_from {
+            Some(token) if token.total != total => {
+                return Err(anyhow!(
+                    "resume token {} is for {} records, not {}",
+                    token,
+                    token.total,
+                    total
+                ))
+            }
+            Some(token) => token.done,
+            None => 0,
+        };
+
+        let mut result = UpsertResult {
+            created: vec![],
+            updated: vec![],
+        };
+        let mut done = start;
+        for group in records[start..].chunks(self.batch_chunk_size * self.batch_concurrency) {
+            let written = self.upsert(group, fields_to_merge_on).await?;
+            result.created.extend(written.created);
+            result.updated.extend(written.updated);
+            done += group.len();
+            checkpoint(ResumeToken { done, total });
+        }
+
+        Ok(result)
+    }
+
+    /// Creates the record unless one with the same value in `unique_field` already exists, in
+    /// which case that one is updated instead. Unlike `create`, sending this again after a
+    /// timeout can't leave a duplicate row behind. Airtable has no idempotency keys for creates,
+    /// so this is an upsert merging on `unique_field`, which must hold a unique value per record.
+    pub async fn create_idempotent(&self, record: &T, unique_field: &str) -> Result<T> {
+        let result = self
+            .upsert(std::slice::from_ref(record), &[unique_field])
+            .await?;
+        result
+            .created
+            .into_iter()
+            .chain(result.updated)
+            .next()
+            .ok_or_else(|| anyhow!("upsert returned no records"))
+    }
+}
+
+/// Copies every record matching `query` into the table behind `to`, and with `delete_source`
+/// deletes the originals afterwards, e.g. to archive them. The destination assigns new ids, so
+/// this returns `(source id, new id)` pairs; its length is the number of records copied.
+///
+/// Nothing is deleted unless every record was copied. All requests go through the respective
+/// base's rate limiter.
+pub async fn copy_records<'base, T>(
+    query: QueryBuilder<'base, T>,
+    to: &Base<T>,
+    delete_source: bool,
+) -> Result<Vec<(String, String)>>
+where
+    for<'de> T: Deserialize<'de>,
+    T: Record + Serialize,
+{
+    let from = query.base;
+    let records = query.collect_all()?;
+    let created = to.create_batch(&records).await?;
+
+    let ids: Vec<(String, String)> = records
+        .iter()
+        .zip(&created)
+        .map(|(source, copy)| (source.id().to_string(), copy.id().to_string()))
+        .collect();
+
+    if delete_source {
+        let source_ids: Vec<&str> = ids.iter().map(|(source, _)| source.as_str()).collect();
+        from.delete_batch(&source_ids).await?;
+    }
+
+    Ok(ids)
+}
@@ -0,0 +1,80 @@
+//! A small bounded cache with least-recently-used eviction and a time to live.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub(crate) struct LruCache<V> {
+    capacity: usize,
+    ttl: Duration,
+    entries: HashMap<String, (Instant, V)>,
+    // Least recently used first. Linear updates are fine for the few hundred entries this is
+    // meant for.
+    order: VecDeque<String>,
+}
+
+impl<V: Clone> LruCache<V> {
+    pub(crate) fn new(capacity: usize, ttl: Duration) -> Self {
+        LruCache {
+            capacity,
+            ttl,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    // An empty cache with the same capacity and time to live.
+    pub(crate) fn emptied(&self) -> Self {
+        LruCache::new(self.capacity, self.ttl)
+    }
+
+    pub(crate) fn get(&mut self, key: &str) -> Option<V> {
+        let (inserted, value) = self.entries.get(key)?;
+        if inserted.elapsed() >= self.ttl {
+            self.remove(key);
+            return None;
+        }
+        let value = value.clone();
+        self.touch(key);
+        Some(value)
+    }
+
+    pub(crate) fn insert(&mut self, key: &str, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self
+            .entries
+            .insert(key.to_owned(), (Instant::now(), value))
+            .is_some()
+        {
+            self.touch(key);
+        } else {
+            self.order.push_back(key.to_owned());
+        }
+        while self.entries.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: &str) {
+        if self.entries.remove(key).is_some() {
+            self.order.retain(|existing| existing != key);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(i) = self.order.iter().position(|existing| existing == key) {
+            if let Some(key) = self.order.remove(i) {
+                self.order.push_back(key);
+            }
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Recording HTTP interactions to a file and replaying them, for tests that run offline and give
+//! the same result every time.
+//!
+//! ```no_run
+//! # use airtable::cassette::CassetteClient;
+//! # use airtable::http::UreqClient;
+//! # #[derive(serde::Deserialize, Default)]
+//! # struct Word { #[serde(skip)] id: airtable::RecordId }
+//! # airtable::impl_record!(Word);
+//! // Talks to Airtable and saves what it said the first time, replays it from then on.
+//! let client = CassetteClient::new("tests/cassettes/words.json", UreqClient::new())?;
+//! let base = airtable::new::<Word>("key123", "app123", "Words").http_client(client);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::http::{HttpClient, Request, Response};
+use crate::Error;
+
+/// What a recorded `Authorization` header is replaced with, so cassettes can be committed.
+pub const REDACTED: &str = "REDACTED";
+
+/// The file format: every request sent and the response it got, in order.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub query: Vec<(String, String)>,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    fn new(request: &Request) -> Self {
+        let headers = request
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                if name.eq_ignore_ascii_case("Authorization") {
+                    (name.clone(), REDACTED.to_owned())
+                } else {
+                    (name.clone(), value.clone())
+                }
+            })
+            .collect();
+        RecordedRequest {
+            method: request.method.clone(),
+            url: request.url.clone(),
+            query: request.query.clone(),
+            headers,
+            body: request.body.clone(),
+        }
+    }
+
+    // Headers aren't compared, so a cassette keeps working when the key or a header changes.
+    fn matches(&self, request: &Request) -> bool {
+        self.method == request.method
+            && self.url == request.url
+            && self.query == request.query
+            && self.body == request.body
+    }
+}
+
+#[derive(Debug)]
+enum Mode {
+    Record(Arc<dyn HttpClient>),
+    Replay,
+}
+
+/// An [`HttpClient`] that either sends requests through another client and records them to a
+/// cassette file, or answers them from one. Replay serves the recorded responses in order and
+/// fails any request that doesn't match the next recorded one by method, url, query and body,
+/// with `Error::NotRecorded`.
+///
+/// The `Authorization` header is saved as [`REDACTED`], but bodies are saved as they are, so
+/// don't record data that shouldn't be committed.
+#[derive(Debug)]
+pub struct CassetteClient {
+    path: PathBuf,
+    mode: Mode,
+    cassette: Mutex<Cassette>,
+    // The next interaction to replay.
+    position: Mutex<usize>,
+}
+
+impl CassetteClient {
+    /// Replays the cassette at `path` if there is one, and records a new one through `client`
+    /// otherwise.
+    pub fn new(path: impl AsRef<Path>, client: impl HttpClient + 'static) -> Result<Self> {
+        if path.as_ref().exists() {
+            CassetteClient::replay(path)
+        } else {
+            Ok(CassetteClient::record(path, client))
+        }
+    }
+
+    /// Sends every request through `client` and saves the cassette to `path` after each one,
+    /// replacing whatever was there.
+    pub fn record(path: impl AsRef<Path>, client: impl HttpClient + 'static) -> Self {
+        CassetteClient {
+            path: path.as_ref().to_owned(),
+            mode: Mode::Record(Arc::new(client)),
+            cassette: Mutex::new(Cassette::default()),
+            position: Mutex::new(0),
+        }
+    }
+
+    /// Answers requests from the cassette at `path`, without any network.
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("reading cassette {}", path.display()))?;
+        let cassette = serde_json::from_str(&json)
+            .with_context(|| format!("parsing cassette {}", path.display()))?;
+        Ok(CassetteClient {
+            path: path.to_owned(),
+            mode: Mode::Replay,
+            cassette: Mutex::new(cassette),
+            position: Mutex::new(0),
+        })
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        matches!(self.mode, Mode::Replay)
+    }
+}
+
+impl HttpClient for CassetteClient {
+    fn send(&self, request: Request) -> Result<Response> {
+        match self.mode {
+            Mode::Record(ref client) => {
+                let recorded = RecordedRequest::new(&request);
+                let response = client.send(request)?;
+                let mut cassette = self.cassette.lock().unwrap();
+                cassette.interactions.push(Interaction {
+                    request: recorded,
+                    response: RecordedResponse {
+                        status: response.status,
+                        headers: response.headers.clone(),
+                        body: response.body.clone(),
+                    },
+                });
+                fs::write(&self.path, serde_json::to_string_pretty(&*cassette)?)
+                    .with_context(|| format!("writing cassette {}", self.path.display()))?;
+                Ok(response)
+            }
+            Mode::Replay => {
+                let cassette = self.cassette.lock().unwrap();
+                let mut position = self.position.lock().unwrap();
+                let interaction =
+                    cassette
+                        .interactions
+                        .get(*position)
+                        .ok_or_else(|| Error::NotRecorded {
+                            reason: format!(
+                                "cassette {} has no more interactions for {} {}",
+                                self.path.display(),
+                                request.method,
+                                request.url
+                            ),
+                        })?;
+                if !interaction.request.matches(&request) {
+                    return Err(Error::NotRecorded {
+                        reason: format!(
+                            "{} {} doesn't match interaction {} of cassette {}, {} {}",
+                            request.method,
+                            request.url,
+                            *position,
+                            self.path.display(),
+                            interaction.request.method,
+                            interaction.request.url
+                        ),
+                    }
+                    .into());
+                }
+                *position += 1;
+                Ok(Response {
+                    status: interaction.response.status,
+                    headers: interaction.response.headers.clone(),
+                    body: interaction.response.body.clone(),
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Where each family of Airtable endpoints lives. Every URL the crate requests is built here.
+
+/// The roots the endpoint URLs are built on. Records, metadata and webhooks share the API root;
+/// attachment uploads go to a separate content host.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoints {
+    pub api: String,
+    pub content: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints {
+            api: String::from("https://api.airtable.com/v0"),
+            content: String::from("https://content.airtable.com/v0"),
+        }
+    }
+}
+
+impl Endpoints {
+    /// Points every endpoint family at one root, e.g. a mock server in tests.
+    pub fn with_root(root: &str) -> Self {
+        let root = root.trim_end_matches('/');
+        Endpoints {
+            api: root.to_owned(),
+            content: root.to_owned(),
+        }
+    }
+
+    /// The host name of the API root, without scheme, port or path.
+    pub fn api_host(&self) -> &str {
+        let rest = match self.api.find("://") {
+            Some(i) => &self.api[i + 3..],
+            None => &self.api,
+        };
+        let end = rest.find(['/', ':']).unwrap_or(rest.len());
+        &rest[..end]
+    }
+
+    pub fn records(&self, app_key: &str, table: &str) -> String {
+        format!("{}/{}/{}", self.api, app_key, path_segment(table))
+    }
+
+    pub fn record(&self, app_key: &str, table: &str, id: &str) -> String {
+        format!("{}/{}", self.records(app_key, table), path_segment(id))
+    }
+
+    pub fn bases(&self) -> String {
+        format!("{}/meta/bases", self.api)
+    }
+
+    pub fn tables(&self, app_key: &str) -> String {
+        format!("{}/meta/bases/{}/tables", self.api, app_key)
+    }
+
+    pub fn webhooks(&self, app_key: &str) -> String {
+        format!("{}/bases/{}/webhooks", self.api, app_key)
+    }
+
+    pub fn webhook_payloads(&self, app_key: &str, webhook_id: &str) -> String {
+        format!("{}/{}/payloads", self.webhooks(app_key), webhook_id)
+    }
+
+    pub fn upload_attachment(&self, app_key: &str, record_id: &str, field: &str) -> String {
+        format!(
+            "{}/{}/{}/{}/uploadAttachment",
+            self.content,
+            app_key,
+            path_segment(record_id),
+            path_segment(field)
+        )
+    }
+}
+
+// Percent-encodes a table or field name for use as one path segment. Left alone, a `#` or `?`
+// in the name would end the path, and a `/` would split it.
+pub(crate) fn path_segment(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
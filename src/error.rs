@@ -0,0 +1,110 @@
+//! Errors specific to this crate. They're returned inside `anyhow::Error`, so match on them with
+//! `err.downcast_ref::<airtable::Error>()`.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The response body was larger than the configured maximum.
+    ResponseTooLarge { limit: u64 },
+    /// A select field value isn't one of the field's options.
+    InvalidSelectOption { option: String },
+    /// The header is set by the crate itself and can't be overridden.
+    ReservedHeader { name: String },
+    /// The fields to write for a record weren't a JSON object. `id` is empty for a new record.
+    FieldsNotAnObject { id: String },
+    /// The operation was stopped through its `CancellationToken`.
+    Cancelled,
+    /// The base has no table with this name or id, or the api key can't see it. Usually a
+    /// wrong app key or table name.
+    TableNotFound { table: String },
+    /// Airtable rejected the api key.
+    Unauthorized,
+    /// The table has no record with this id.
+    RecordNotFound { id: String },
+    /// A record had fields the record type doesn't list in `Record::field_names`.
+    UnknownFields { id: String, fields: Vec<String> },
+    /// A conditional update's predicate rejected the record as currently stored.
+    PreconditionFailed { id: String },
+    /// A record was written by id, but its id was empty. Records that haven't been created yet
+    /// have no id.
+    MissingId,
+    /// The query's options can't work together or are out of range, caught before sending.
+    InvalidQuery { reason: String },
+    /// A string parsed as a `RecordId` doesn't look like one.
+    InvalidRecordId { id: String },
+    /// Airtable rejected a write for leaving out fields it needs. `fields` has the names its
+    /// message gave, which may be none.
+    MissingRequiredFields { fields: Vec<String> },
+    /// Another write kept overwriting a read-modify-write update of the record, even after
+    /// retrying.
+    ConcurrentWrite { id: String },
+    /// Records collected into a map had the same key. `key` is the key's `Debug` form and `ids`
+    /// the records that had it.
+    DuplicateKey { key: String, ids: Vec<String> },
+    /// A rating is above the field's maximum, or zero, which Airtable doesn't store.
+    RatingOutOfRange { rating: u8, max: u8 },
+    /// A formula template couldn't be bound to its arguments.
+    InvalidFormula { reason: String },
+    /// A raw request's HTTP method isn't one Airtable's API uses.
+    InvalidMethod { method: String },
+    /// A replaying `CassetteClient` was sent a request it has no recording of.
+    NotRecorded { reason: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ResponseTooLarge { limit } => {
+                write!(f, "response body exceeded the {} byte limit", limit)
+            }
+            Error::InvalidSelectOption { option } => {
+                write!(f, "{:?} is not one of the field's select options", option)
+            }
+            Error::ReservedHeader { name } => write!(f, "the {} header can't be overridden", name),
+            Error::FieldsNotAnObject { id } if id.is_empty() => {
+                f.write_str("fields must be a JSON object")
+            }
+            Error::FieldsNotAnObject { id } => {
+                write!(f, "fields for record {:?} must be a JSON object", id)
+            }
+            Error::Cancelled => f.write_str("operation was cancelled"),
+            Error::TableNotFound { table } => write!(f, "table {:?} not found", table),
+            Error::Unauthorized => f.write_str("the api key was rejected"),
+            Error::RecordNotFound { id } => write!(f, "record {:?} not found", id),
+            Error::UnknownFields { id, fields } => {
+                write!(f, "record {:?} has unknown fields {:?}", id, fields)
+            }
+            Error::PreconditionFailed { id } => {
+                write!(
+                    f,
+                    "record {:?} no longer meets the update's precondition",
+                    id
+                )
+            }
+            Error::MissingId => f.write_str("the record has no id to update"),
+            Error::InvalidQuery { reason } => write!(f, "invalid query: {}", reason),
+            Error::InvalidRecordId { id } => write!(f, "{:?} is not a record id", id),
+            Error::MissingRequiredFields { fields } if fields.is_empty() => {
+                f.write_str("the request is missing required fields")
+            }
+            Error::MissingRequiredFields { fields } => {
+                write!(f, "the request is missing required fields {:?}", fields)
+            }
+            Error::ConcurrentWrite { id } => {
+                write!(f, "record {:?} kept being changed by another write", id)
+            }
+            Error::DuplicateKey { key, ids } => {
+                write!(f, "records {:?} share the key {}", ids, key)
+            }
+            Error::RatingOutOfRange { rating, max } => {
+                write!(f, "rating {} is not between 1 and {}", rating, max)
+            }
+            Error::InvalidFormula { reason } => write!(f, "invalid formula: {}", reason),
+            Error::InvalidMethod { method } => write!(f, "{:?} is not an HTTP method", method),
+            Error::NotRecorded { reason } => f.write_str(reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,658 @@
+//! Typed wrappers for Airtable field types whose JSON shape doesn't say enough on its own.
+
+use anyhow::Result;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::time::SystemTime;
+
+use crate::Error;
+
+/// A single select field. Airtable sends the chosen option's name as a plain string.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[serde(transparent)]
+pub struct SingleSelect(pub String);
+
+impl SingleSelect {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Checks the value is one of `options`, catching typos before Airtable rejects the write
+    /// (or, with `typecast`, quietly adds a new option).
+    pub fn validate(&self, options: &[&str]) -> Result<()> {
+        validate_option(&self.0, options)
+    }
+}
+
+/// A multiple select field, sent as an array of option names. Airtable leaves the field out
+/// entirely when nothing is selected, so pair it with `#[serde(default)]`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[serde(transparent)]
+pub struct MultiSelect(pub Vec<String>);
+
+impl MultiSelect {
+    pub fn contains(&self, option: &str) -> bool {
+        self.0.iter().any(|selected| selected == option)
+    }
+
+    pub fn validate(&self, options: &[&str]) -> Result<()> {
+        self.0
+            .iter()
+            .try_for_each(|selected| validate_option(selected, options))
+    }
+}
+
+fn validate_option(value: &str, options: &[&str]) -> Result<()> {
+    if options.contains(&value) {
+        Ok(())
+    } else {
+        Err(Error::InvalidSelectOption {
+            option: value.to_owned(),
+        }
+        .into())
+    }
+}
+
+/// One file in an attachment field, which holds an array of them. The `url` and thumbnail urls
+/// Airtable returns are temporary (they expire after a few hours), so download or re-fetch
+/// rather than storing them.
+///
+/// To attach a new file, only `url` (and optionally `filename`) need to be set; Airtable fills
+/// in the rest.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Attachment {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Only present for images and a few document types.
+    #[serde(default, skip_serializing)]
+    pub thumbnails: Option<Thumbnails>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Thumbnails {
+    #[serde(default)]
+    pub small: Option<Thumbnail>,
+    #[serde(default)]
+    pub large: Option<Thumbnail>,
+    #[serde(default)]
+    pub full: Option<Thumbnail>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Thumbnail {
+    pub url: String,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+}
+
+/// A barcode field: the scanned text and, when known, the symbology it was read as (e.g.
+/// `"upce"` or `"code39"`).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Barcode {
+    pub text: String,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub symbology: Option<String>,
+}
+
+/// A button field. Buttons are computed, so they can be read but not written. `url` is only set
+/// for buttons that open a URL.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Button {
+    pub label: String,
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+/// A user in a collaborator field, which holds one of these, or an array of them when it allows
+/// several. Airtable sends the id, email and name; writing sends only the id, or the email when
+/// there's no id, which is all Airtable looks at.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Collaborator {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+impl Collaborator {
+    /// A collaborator to write by user id, e.g. `usrGr8gG8KaJsr3ne`.
+    pub fn by_id(id: impl Into<String>) -> Self {
+        Collaborator {
+            id: Some(id.into()),
+            ..Collaborator::default()
+        }
+    }
+
+    /// A collaborator to write by email. The user must already be a collaborator on the base.
+    pub fn by_email(email: impl Into<String>) -> Self {
+        Collaborator {
+            email: Some(email.into()),
+            ..Collaborator::default()
+        }
+    }
+}
+
+impl Serialize for Collaborator {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(1))?;
+        match (&self.id, &self.email) {
+            (Some(id), _) => map.serialize_entry("id", id)?,
+            (None, Some(email)) => map.serialize_entry("email", email)?,
+            (None, None) => {
+                return Err(serde::ser::Error::custom(
+                    "a collaborator needs an id or an email",
+                ))
+            }
+        }
+        map.end()
+    }
+}
+
+/// A duration field. Airtable sends durations as a number of seconds, which has a fractional
+/// part when the field's format shows tenths of a second or finer, and can be negative.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[serde(transparent)]
+pub struct Duration(pub f64);
+
+impl Duration {
+    pub fn as_secs_f64(&self) -> f64 {
+        self.0
+    }
+
+    /// `None` for negative durations, which `std::time::Duration` can't hold.
+    pub fn to_std(&self) -> Option<std::time::Duration> {
+        std::time::Duration::try_from_secs_f64(self.0).ok()
+    }
+}
+
+impl From<std::time::Duration> for Duration {
+    fn from(duration: std::time::Duration) -> Self {
+        Duration(duration.as_secs_f64())
+    }
+}
+
+/// Formats as `h:mm:ss`, like the field's default format, with milliseconds added when there
+/// are any, e.g. `1:02:03.500`.
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = (self.0 * 1000.0).round() as i64;
+        let sign = if millis < 0 { "-" } else { "" };
+        let millis = millis.unsigned_abs();
+        let secs = millis / 1000;
+        write!(
+            f,
+            "{}{}:{:02}:{:02}",
+            sign,
+            secs / 3600,
+            secs % 3600 / 60,
+            secs % 60
+        )?;
+        match millis % 1000 {
+            0 => Ok(()),
+            fraction => write!(f, ".{:03}", fraction),
+        }
+    }
+}
+
+/// A currency field. Airtable sends the amount as a plain number; the symbol and precision are
+/// part of the field's settings, not its values, so formatting takes them as arguments.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[serde(transparent)]
+pub struct Currency(pub f64);
+
+impl Currency {
+    pub fn amount(&self) -> f64 {
+        self.0
+    }
+
+    /// Formats the amount the way Airtable displays it, e.g. `-$1,234.50` for
+    /// `format("$", 2)`.
+    pub fn format(&self, symbol: &str, precision: usize) -> String {
+        let digits = format!("{:.*}", precision, self.0.abs());
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (digits.as_str(), None),
+        };
+
+        let mut formatted = String::new();
+        if self.0 < 0.0 && digits.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+            formatted.push('-');
+        }
+        formatted.push_str(symbol);
+        for (i, digit) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i) % 3 == 0 {
+                formatted.push(',');
+            }
+            formatted.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            formatted.push('.');
+            formatted.push_str(fraction);
+        }
+        formatted
+    }
+
+    /// The amount in the currency's smallest unit, e.g. `1999` cents for `19.99` with a
+    /// precision of 2. Rounds in decimal, the way the amount displays, where scaling the float
+    /// doesn't: `19.99 * 100.0` is `1998.9999999999998`, which truncates to the wrong cent.
+    /// `None` when the amount doesn't fit in an `i64`.
+    ///
+    /// Keep money in minor units (or a decimal type) rather than adding up `f64` amounts, which
+    /// drift by fractions of a cent.
+    pub fn minor_units(&self, precision: usize) -> Option<i64> {
+        format!("{:.*}", precision, self.0)
+            .replace('.', "")
+            .parse()
+            .ok()
+    }
+}
+
+/// A percent field. Airtable sends and expects the fraction, not the percentage: 75% is `0.75`,
+/// so writing `75.0` stores 7500%. Use `from_percentage` and `percentage` to convert.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[serde(transparent)]
+pub struct Percent(pub f64);
+
+impl Percent {
+    /// E.g. `Percent::from_percentage(75.0)` for 75%, which is `Percent(0.75)`.
+    pub fn from_percentage(percentage: f64) -> Self {
+        Percent(percentage / 100.0)
+    }
+
+    pub fn fraction(&self) -> f64 {
+        self.0
+    }
+
+    pub fn percentage(&self) -> f64 {
+        self.0 * 100.0
+    }
+
+    /// Formats the percentage the way Airtable displays it, e.g. `75.5%` for `format(1)`.
+    pub fn format(&self, precision: usize) -> String {
+        format!("{:.*}%", precision, self.percentage())
+    }
+}
+
+/// A rating field, a whole number from 1 up to the field's maximum (at most 10). The maximum
+/// is part of the field's settings, so `validate` takes it as an argument. Airtable leaves the
+/// field out when there's no rating, so pair it with `Option` and `#[serde(default)]`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(transparent)]
+pub struct Rating(pub u8);
+
+impl Rating {
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    /// Checks the rating is between 1 and `max`, catching values Airtable would reject.
+    pub fn validate(&self, max: u8) -> Result<()> {
+        if (1..=max).contains(&self.0) {
+            Ok(())
+        } else {
+            Err(Error::RatingOutOfRange {
+                rating: self.0,
+                max,
+            }
+            .into())
+        }
+    }
+
+    /// Draws the rating as stars out of `max`, e.g. `★★★☆☆` for `Rating(3).stars(5)`.
+    pub fn stars(&self, max: u8) -> String {
+        let filled = self.0.min(max);
+        "★".repeat(filled.into()) + &"☆".repeat((max - filled).into())
+    }
+}
+
+/// The value of a formula, rollup or lookup field, whose type depends on how the field is set
+/// up and can change when someone edits it. A formula that fails in a cell comes back as an
+/// object like `{ "error": "#ERROR!" }` instead of a value; deserializing that as `Error` rather
+/// than failing keeps one bad cell from failing the whole page.
+///
+/// Airtable leaves the field out when the result is empty, so pair it with `Option` and
+/// `#[serde(default)]`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum FormulaResult {
+    Error { error: String },
+    Number(f64),
+    Bool(bool),
+    Text(String),
+    Array(Vec<FormulaResult>),
+}
+
+impl FormulaResult {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            FormulaResult::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            FormulaResult::Text(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The error code, e.g. `#ERROR!` or `#DIV/0!`, for a cell whose formula failed.
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            FormulaResult::Error { error } => Some(error),
+            _ => None,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.error().is_some()
+    }
+}
+
+/// A field that a query may not have selected, for record types read both whole and through
+/// `QueryBuilder::fields`. Use it with `#[serde(default)]` and a query made with
+/// `QueryBuilder::empty_fields_as_null`, which fills in `null` for selected fields that are
+/// empty, so a missing field can only mean it wasn't selected:
+///
+/// ```
+/// # use serde::Deserialize;
+/// use airtable::fields::Selected;
+///
+/// #[derive(Deserialize)]
+/// struct Person {
+///     #[serde(rename = "Nickname", default)]
+///     nickname: Selected<String>,
+/// }
+/// ```
+///
+/// A plain `Option` can't make the distinction: it's `None` both ways. Without
+/// `empty_fields_as_null`, empty fields come out as `NotSelected` too. Writing skips
+/// `NotSelected` fields when paired with `skip_serializing_if = "Selected::is_not_selected"`, and
+/// clears `Empty` ones.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Selected<T> {
+    /// The query didn't ask for the field.
+    #[default]
+    NotSelected,
+    /// The query asked for the field and it's empty.
+    Empty,
+    Value(T),
+}
+
+impl<T> Selected<T> {
+    pub fn is_not_selected(&self) -> bool {
+        matches!(self, Selected::NotSelected)
+    }
+
+    pub fn value(&self) -> Option<&T> {
+        match self {
+            Selected::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The value, or `None` whether the field is empty or wasn't selected.
+    pub fn into_option(self) -> Option<T> {
+        match self {
+            Selected::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Selected<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(match Option::<T>::deserialize(deserializer)? {
+            Some(value) => Selected::Value(value),
+            None => Selected::Empty,
+        })
+    }
+}
+
+impl<T: Serialize> Serialize for Selected<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Selected::Value(value) => value.serialize(serializer),
+            _ => serializer.serialize_none(),
+        }
+    }
+}
+
+/// Deserializes an empty string as `None`. Airtable usually leaves cleared text fields out of
+/// the response, which `#[serde(default)]` turns into `None`, but some fields come back as `""`
+/// instead and would otherwise be `Some("")`. Use both together:
+///
+/// ```
+/// # use serde::Deserialize;
+/// #[derive(Deserialize)]
+/// struct Person {
+///     #[serde(default, deserialize_with = "airtable::fields::deserialize_empty_as_none")]
+///     nickname: Option<String>,
+/// }
+/// ```
+pub fn deserialize_empty_as_none<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    Ok(value.filter(|value| !value.is_empty()))
+}
+
+/// Serializes a string bound for a number field as a JSON number when it parses as one, so
+/// values read from a CSV can be written without converting them first. Surrounding whitespace
+/// is ignored and an empty string clears the field. Anything else is sent as the string itself,
+/// which Airtable rejects unless the base has `typecast` enabled, in which case it tries to
+/// convert it.
+///
+/// ```
+/// # use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct Row {
+///     #[serde(serialize_with = "airtable::fields::serialize_number_from_str")]
+///     price: String,
+/// }
+/// ```
+pub fn serialize_number_from_str<S>(value: &str, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return serializer.serialize_none();
+    }
+    if let Ok(integer) = trimmed.parse::<i64>() {
+        return serializer.serialize_i64(integer);
+    }
+    match trimmed.parse::<f64>() {
+        Ok(number) if number.is_finite() => serializer.serialize_f64(number),
+        _ => serializer.serialize_str(value),
+    }
+}
+
+/// Deserializes a whole-number field, such as a number field holding an external id, into an
+/// `i64`. Integers are read exactly, where an `f64` rounds anything past 2^53, e.g.
+/// `9007199254740993`. A value with a fractional part, or a float too large to still be exact,
+/// fails instead of being rounded.
+///
+/// ```
+/// # use serde::Deserialize;
+/// #[derive(Deserialize)]
+/// struct Order {
+///     #[serde(rename = "Order number")]
+///     #[serde(deserialize_with = "airtable::fields::deserialize_exact_i64")]
+///     number: i64,
+/// }
+/// ```
+///
+/// For decimal amounts, see `Currency::minor_units`, or `deserialize_decimal` with the
+/// `decimal` feature.
+pub fn deserialize_exact_i64<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct ExactI64;
+
+    impl serde::de::Visitor<'_> for ExactI64 {
+        type Value = i64;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a whole number")
+        }
+
+        fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<i64, E> {
+            Ok(value)
+        }
+
+        fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<i64, E> {
+            std::convert::TryFrom::try_from(value)
+                .map_err(|_| E::custom(format!("{} is too large", value)))
+        }
+
+        fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<i64, E> {
+            // Past 2^53 a float no longer holds every integer, so it may already be rounded.
+            const EXACT_LIMIT: f64 = 9_007_199_254_740_992.0;
+            if value.fract() != 0.0 || value.abs() > EXACT_LIMIT {
+                return Err(E::custom(format!("{} is not an exact whole number", value)));
+            }
+            Ok(value as i64)
+        }
+    }
+
+    deserializer.deserialize_any(ExactI64)
+}
+
+/// Deserializes a number field, such as a currency or percent field, into a
+/// `rust_decimal::Decimal`, so amounts add up exactly where `f64`s drift: `0.1 + 0.2` is `0.3`
+/// rather than `0.30000000000000004`. Numbers are read as the shortest decimal that round-trips
+/// through the JSON value, which is the value Airtable displays; strings, e.g. from a formula
+/// field, are parsed. Needs the `decimal` feature.
+///
+/// ```
+/// # use serde::{Deserialize, Serialize};
+/// # use rust_decimal::Decimal;
+/// #[derive(Deserialize, Serialize)]
+/// struct Invoice {
+///     #[serde(rename = "Total")]
+///     #[serde(deserialize_with = "airtable::fields::deserialize_decimal")]
+///     #[serde(serialize_with = "airtable::fields::serialize_decimal")]
+///     total: Decimal,
+/// }
+/// ```
+#[cfg(feature = "decimal")]
+pub fn deserialize_decimal<'de, D>(deserializer: D) -> Result<rust_decimal::Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use rust_decimal::Decimal;
+
+    struct DecimalVisitor;
+
+    impl serde::de::Visitor<'_> for DecimalVisitor {
+        type Value = Decimal;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a number")
+        }
+
+        fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Decimal, E> {
+            Ok(Decimal::from(value))
+        }
+
+        fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Decimal, E> {
+            Ok(Decimal::from(value))
+        }
+
+        fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<Decimal, E> {
+            // `Display` gives the shortest digits that read back as the same float.
+            self.visit_str(&value.to_string())
+        }
+
+        fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Decimal, E> {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| E::custom(format!("{:?} is not a decimal number", value)))
+        }
+    }
+
+    deserializer.deserialize_any(DecimalVisitor)
+}
+
+/// Serializes a `rust_decimal::Decimal` as a JSON number, which number fields require, where
+/// `Decimal`'s own `Serialize` writes a string. Digits past an `f64`'s precision, about 15
+/// significant ones, are rounded, as Airtable would store them anyway. Needs the `decimal`
+/// feature.
+#[cfg(feature = "decimal")]
+pub fn serialize_decimal<S>(value: &rust_decimal::Decimal, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if value.scale() == 0 {
+        if let Ok(whole) = value.to_string().parse::<i64>() {
+            return serializer.serialize_i64(whole);
+        }
+    }
+    let number: f64 = value
+        .to_string()
+        .parse()
+        .map_err(|_| serde::ser::Error::custom(format!("{} is out of range", value)))?;
+    serializer.serialize_f64(number)
+}
+
+/// Deserializes a date-time field, such as a "Last modified time" field, into a `SystemTime`.
+/// Airtable sends these as ISO 8601 strings in UTC, e.g. `"2021-04-01T12:30:00.000Z"`, and
+/// leaves them out until there's something to show (a last-modified field watching specific
+/// columns stays empty until one of them is edited), so pair it with `#[serde(default)]`:
+///
+/// ```
+/// # use serde::Deserialize;
+/// # use std::time::SystemTime;
+/// #[derive(Deserialize)]
+/// struct Task {
+///     #[serde(rename = "Status modified", default)]
+///     #[serde(deserialize_with = "airtable::fields::deserialize_timestamp")]
+///     status_modified: Option<SystemTime>,
+/// }
+/// ```
+///
+/// With `chrono`'s `serde` feature enabled, `Option<chrono::DateTime<chrono::Utc>>` reads the
+/// same strings directly, with no helper needed.
+pub fn deserialize_timestamp<'de, D>(deserializer: D) -> Result<Option<SystemTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) if !value.is_empty() => crate::formula::parse_timestamp(&value)
+            .map(Some)
+            .ok_or_else(|| D::Error::custom(format!("invalid timestamp {:?}", value))),
+        _ => Ok(None),
+    }
+}
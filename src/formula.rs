@@ -0,0 +1,290 @@
+//! Helpers for building `filterByFormula` expressions.
+
+use anyhow::Result;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::Error;
+
+/// The name Airtable suggests for a "Created time" field when someone adds one to a table.
+/// Tables don't have the field unless it's added, and it may be named anything.
+pub const CREATED_FIELD: &str = "Created";
+
+/// The record's creation time, usable anywhere in a formula.
+pub const CREATED_TIME: &str = "CREATED_TIME()";
+
+/// Matches records created within the last `days` days, e.g. `created_in_last_days(7)`.
+pub fn created_in_last_days(days: u32) -> String {
+    format!(
+        "IS_AFTER({}, DATEADD(NOW(), -{}, 'days'))",
+        CREATED_TIME, days
+    )
+}
+
+/// Matches records created before `days` days ago.
+pub fn created_before_days_ago(days: u32) -> String {
+    format!(
+        "IS_BEFORE({}, DATEADD(NOW(), -{}, 'days'))",
+        CREATED_TIME, days
+    )
+}
+
+/// References a field by name, escaping any closing braces in it, e.g. `{Last Modified}`.
+pub fn field(name: &str) -> String {
+    format!("{{{}}}", name.replace('}', "\\}"))
+}
+
+/// Matches records whose last-modified field is after `since`. Airtable has no native
+/// modified-since filter, so the table needs a "Last modified time" field, named by `field`.
+pub fn modified_since(field_name: &str, since: SystemTime) -> String {
+    format!(
+        "IS_AFTER({}, {})",
+        field(field_name),
+        FormulaValue::Date(since).render()
+    )
+}
+
+/// Matches records whose date field `field_name` falls strictly between `start` and `end`, e.g.
+/// for "records from last week" with a "Created time" or "Last modified time" field. Both times
+/// are sent in UTC with an explicit `Z`, e.g. `2021-04-01T00:00:00.000Z`, so the result doesn't
+/// depend on the base's or the field's time zone. A date-only field compares as midnight UTC.
+pub fn between_dates(field_name: &str, start: SystemTime, end: SystemTime) -> String {
+    format!(
+        "AND(IS_AFTER({field}, {}), IS_BEFORE({field}, {}))",
+        FormulaValue::Date(start).render(),
+        FormulaValue::Date(end).render(),
+        field = field(field_name)
+    )
+}
+
+/// A value to bind into a formula template with [`bind`], rendered as an Airtable literal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormulaValue {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Date(SystemTime),
+}
+
+impl FormulaValue {
+    fn render(&self) -> String {
+        match self {
+            FormulaValue::String(value) => quote(value),
+            FormulaValue::Number(value) => value.to_string(),
+            FormulaValue::Bool(true) => String::from("TRUE()"),
+            FormulaValue::Bool(false) => String::from("FALSE()"),
+            FormulaValue::Date(time) => format!("DATETIME_PARSE({})", quote(&timestamp(*time))),
+        }
+    }
+}
+
+impl From<&str> for FormulaValue {
+    fn from(value: &str) -> Self {
+        FormulaValue::String(value.to_owned())
+    }
+}
+
+impl From<String> for FormulaValue {
+    fn from(value: String) -> Self {
+        FormulaValue::String(value)
+    }
+}
+
+impl From<f64> for FormulaValue {
+    fn from(value: f64) -> Self {
+        FormulaValue::Number(value)
+    }
+}
+
+impl From<i64> for FormulaValue {
+    fn from(value: i64) -> Self {
+        FormulaValue::Number(value as f64)
+    }
+}
+
+impl From<bool> for FormulaValue {
+    fn from(value: bool) -> Self {
+        FormulaValue::Bool(value)
+    }
+}
+
+impl From<SystemTime> for FormulaValue {
+    fn from(value: SystemTime) -> Self {
+        FormulaValue::Date(value)
+    }
+}
+
+/// Quotes a string as a formula literal, escaping backslashes and double quotes.
+pub fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Replaces each `?` in `template` with the next argument, rendered as a properly quoted and
+/// escaped literal. A `?` inside a quoted string in the template is left alone.
+///
+/// Fails with `Error::InvalidFormula` if the number of placeholders doesn't match the number of
+/// arguments, or a number is NaN or infinite, which formulas can't express.
+///
+/// ```
+/// use airtable::formula::{bind, FormulaValue};
+///
+/// let formula = bind("AND({Name} = ?, {Age} > ?)", &["O'Brien \"Bob\"".into(), 30.into()])?;
+/// assert_eq!(formula, r#"AND({Name} = "O'Brien \"Bob\"", {Age} > 30)"#);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn bind(template: &str, args: &[FormulaValue]) -> Result<String> {
+    Ok(try_bind(template, args)?)
+}
+
+// `bind` with the crate's own error, for `QueryBuilder::formula_args` to hold on to.
+pub(crate) fn try_bind(template: &str, args: &[FormulaValue]) -> Result<String, Error> {
+    let invalid = |reason: String| Error::InvalidFormula { reason };
+    let mut formula = String::with_capacity(template.len());
+    let given = args.len();
+    let mut args = args.iter();
+    let mut placeholders = 0;
+    let mut quote_char = None;
+    let mut escaped = false;
+
+    for c in template.chars() {
+        match quote_char {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote_char = None;
+                }
+                formula.push(c);
+            }
+            None => match c {
+                '"' | '\'' => {
+                    quote_char = Some(c);
+                    formula.push(c);
+                }
+                '?' => {
+                    placeholders += 1;
+                    let arg = match args.next() {
+                        Some(arg) => arg,
+                        None => continue,
+                    };
+                    if let FormulaValue::Number(number) = arg {
+                        if !number.is_finite() {
+                            return Err(invalid(format!(
+                                "argument {} is {}, which formulas can't express",
+                                placeholders, number
+                            )));
+                        }
+                    }
+                    formula.push_str(&arg.render());
+                }
+                _ => formula.push(c),
+            },
+        }
+    }
+
+    if placeholders != given {
+        return Err(invalid(format!(
+            "the template takes {} arguments, not {}",
+            placeholders, given
+        )));
+    }
+    Ok(formula)
+}
+
+// Renders a time the way Airtable expects in formulas, e.g. `2021-04-01T12:30:00.000Z`.
+pub(crate) fn timestamp(time: SystemTime) -> String {
+    let (secs, millis) = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => (duration.as_secs() as i64, duration.subsec_millis()),
+        Err(err) => {
+            let duration = err.duration();
+            let mut secs = -(duration.as_secs() as i64);
+            let mut millis = duration.subsec_millis();
+            if millis > 0 {
+                secs -= 1;
+                millis = 1000 - millis;
+            }
+            (secs, millis)
+        }
+    };
+
+    let days = secs.div_euclid(86_400);
+    let rem = secs.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        millis
+    )
+}
+
+// Parses the timestamps `timestamp` produces, which is also how Airtable sends date-time field
+// values. The fraction is optional and read to millisecond precision.
+pub(crate) fn parse_timestamp(value: &str) -> Option<SystemTime> {
+    let (date, time) = value.strip_suffix('Z')?.split_once('T')?;
+
+    let mut date = date.splitn(3, '-').map(str::parse::<u32>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut time = time.splitn(3, ':').map(str::parse::<u64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+    if second > 60 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let millis: u64 = format!("{:0<3}", &fraction[..fraction.len().min(3)])
+        .parse()
+        .ok()?;
+
+    let days = days_from_civil(i64::from(year), month, day);
+    let secs = days * 86_400 + (hour * 3600 + minute * 60 + second) as i64;
+    let millis = secs * 1000 + millis as i64;
+    let since_epoch = Duration::from_millis(millis.unsigned_abs());
+    if millis >= 0 {
+        UNIX_EPOCH.checked_add(since_epoch)
+    } else {
+        UNIX_EPOCH.checked_sub(since_epoch)
+    }
+}
+
+// The inverse of `civil_from_days`.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from(if month > 2 { month - 3 } else { month + 9 });
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// Howard Hinnant's days-to-civil algorithm, for days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
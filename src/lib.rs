@@ -1,18 +1,152 @@
 //! License: MIT
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::marker::PhantomData;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
-use tracing::debug;
+use tracing::{debug, warn};
+
+pub mod batch;
+mod cache;
+mod cancel;
+pub mod cassette;
+mod endpoints;
+mod error;
+pub mod fields;
+pub mod formula;
+pub mod http;
+pub mod metadata;
+mod ndjson;
+mod pagination;
+mod rate_limit;
+mod record_id;
+mod retry;
+pub mod webhooks;
+
+use cache::LruCache;
+pub use cancel::CancellationToken;
+pub use endpoints::Endpoints;
+pub use error::Error;
+use http::{HttpClient, Request, Response, UreqClient};
+use pagination::CursorPage;
+pub use rate_limit::{RateLimitState, RateLimiter, AIRTABLE_REQUESTS_PER_SECOND};
+pub use record_id::RecordId;
+pub use retry::{Attempt, RetryDecider, RetryPolicy};
+
+// The page bodies of each cached query, in order.
+type PageCache = LruCache<Arc<Vec<String>>>;
+
+/// The default cap on response bodies, see `Base::max_response_size`.
+pub const DEFAULT_MAX_RESPONSE_SIZE: u64 = 50 * 1024 * 1024;
+
+// Where `QueryBuilder::adaptive_page_size` starts without a page size, and the bounds it keeps
+// to, slowing from the top when a page takes longer than `SLOW_PAGE`.
+const ADAPTIVE_FIRST_PAGE_SIZE: usize = 25;
+const ADAPTIVE_MIN_PAGE_SIZE: usize = 10;
+const MAX_PAGE_SIZE: usize = 100;
+const SLOW_PAGE: Duration = Duration::from_secs(1);
+
+// 50 quoted record ids keep a `get_many` formula around 1.5KB, well under the URL limit.
+const GET_MANY_CHUNK_SIZE: usize = 50;
+
+/// How many chunk requests a batch operation has in flight at once by default, see
+/// `Base::batch_concurrency`.
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 2;
 
-const URL: &str = "https://api.airtable.com/v0";
 #[derive(Debug)]
 pub struct Base<T: Record> {
     table: String,
     api_key: String,
     app_key: String,
-    phantom: PhantomData<T>,
+    http: Arc<dyn HttpClient>,
+    max_response_size: u64,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    rate_limiter: Option<RateLimiter>,
+    retry: Arc<dyn RetryDecider>,
+    typecast: bool,
+    canonical_json: bool,
+    batch_concurrency: usize,
+    batch_chunk_size: usize,
+    batch_retry_budget: Option<usize>,
+    batch_progress: Option<batch::ProgressHook>,
+    request_hook: Option<http::RequestHook>,
+    headers: Vec<(String, String)>,
+    endpoints: Endpoints,
+    schema_cache: Option<metadata::SchemaCache>,
+    // Raw record responses by id, so hits don't need `T: Clone`.
+    record_cache: Option<Arc<Mutex<LruCache<String>>>>,
+    // Raw page bodies by query, for the same reason.
+    query_cache: Option<Arc<Mutex<PageCache>>>,
+    // A base never holds a `T`, so it's `Send` and `Sync` whatever the record type is.
+    phantom: PhantomData<fn() -> T>,
+}
+
+// Clones share the transport, rate limiter and caches.
+impl<T: Record> Clone for Base<T> {
+    fn clone(&self) -> Self {
+        Base {
+            table: self.table.clone(),
+            api_key: self.api_key.clone(),
+            app_key: self.app_key.clone(),
+            http: self.http.clone(),
+            max_response_size: self.max_response_size,
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+            rate_limiter: self.rate_limiter.clone(),
+            retry: self.retry.clone(),
+            typecast: self.typecast,
+            canonical_json: self.canonical_json,
+            batch_concurrency: self.batch_concurrency,
+            batch_chunk_size: self.batch_chunk_size,
+            batch_retry_budget: self.batch_retry_budget,
+            batch_progress: self.batch_progress.clone(),
+            request_hook: self.request_hook.clone(),
+            headers: self.headers.clone(),
+            endpoints: self.endpoints.clone(),
+            schema_cache: self.schema_cache.clone(),
+            record_cache: self.record_cache.clone(),
+            query_cache: self.query_cache.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// A result along with how long the request behind it took and how many attempts it needed.
+/// Returned by the `*_timed` variants of the `Base` methods.
+#[derive(Debug, Clone)]
+pub struct Timed<T> {
+    pub value: T,
+    pub elapsed: Duration,
+    /// How much of `elapsed` went on waiting for the base's `RateLimiter`.
+    pub throttled: Duration,
+    pub attempts: u32,
+}
+
+impl<T> Timed<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Timed<U> {
+        Timed {
+            value: f(self.value),
+            elapsed: self.elapsed,
+            throttled: self.throttled,
+            attempts: self.attempts,
+        }
+    }
+
+    fn with_value<U>(&self, value: U) -> Timed<U> {
+        Timed {
+            value,
+            elapsed: self.elapsed,
+            throttled: self.throttled,
+            attempts: self.attempts,
+        }
+    }
 }
 
 pub fn new<T>(api_key: &str, app_key: &str, table: &str) -> Base<T>
@@ -23,6 +157,24 @@ where
         api_key: api_key.to_owned(),
         app_key: app_key.to_owned(),
         table: table.to_owned(),
+        http: Arc::new(UreqClient::new()),
+        max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+        read_timeout: None,
+        write_timeout: None,
+        rate_limiter: None,
+        retry: Arc::new(RetryPolicy::default()),
+        typecast: false,
+        canonical_json: false,
+        batch_concurrency: DEFAULT_BATCH_CONCURRENCY,
+        batch_chunk_size: batch::BATCH_SIZE,
+        batch_retry_budget: None,
+        batch_progress: None,
+        request_hook: None,
+        headers: vec![],
+        endpoints: Endpoints::default(),
+        schema_cache: None,
+        record_cache: None,
+        query_cache: None,
         phantom: PhantomData,
     }
 }
@@ -32,6 +184,39 @@ struct SRecord<T> {
     #[serde(default, skip_serializing)]
     id: String,
     fields: T,
+
+    #[serde(rename = "createdTime", default, skip_serializing)]
+    created_time: Option<String>,
+    // Only present when asked for with `recordMetadata[]=commentCount`.
+    #[serde(rename = "commentCount", default, skip_serializing)]
+    comment_count: Option<u64>,
+}
+
+// Reads and writes convert through these two so no path has to remember to carry the id across
+// by hand.
+impl<'a, T: Record> From<&'a T> for SRecord<&'a T> {
+    fn from(record: &'a T) -> Self {
+        SRecord {
+            id: record.id().to_string(),
+            fields: record,
+            created_time: None,
+            comment_count: None,
+        }
+    }
+}
+
+impl<T: Record> SRecord<T> {
+    fn into_record(self) -> T {
+        let mut record = self.fields;
+        record.set_id(RecordId::from(self.id));
+        if let Some(created_time) = self.created_time {
+            record.set_created_time(created_time);
+        }
+        if let Some(comment_count) = self.comment_count {
+            record.set_comment_count(comment_count);
+        }
+        record
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -42,12 +227,42 @@ struct RecordPage<T> {
     offset: String,
 }
 
+impl<T> CursorPage for RecordPage<T> {
+    type Item = SRecord<T>;
+    type Cursor = String;
+
+    fn into_parts(self) -> (Vec<SRecord<T>>, Option<String>) {
+        let offset = if self.offset.is_empty() {
+            None
+        } else {
+            Some(self.offset)
+        };
+        (self.records, offset)
+    }
+}
+
+/// Iterates over every record matching a query, fetching a page at a time as the previous one
+/// runs out. Each page fetch waits for a permit from the base's `RateLimiter`, if it has one, so
+/// draining a whole table as fast as possible still stays under the configured rate.
+///
+/// There's no async `Stream` counterpart: requests are blocking, so in async code drive the
+/// iterator from a blocking task (e.g. `tokio::task::spawn_blocking`).
 pub struct Paginator<'base, T: Record> {
     base: &'base Base<T>,
     // TODO: Move the offset to query_builder
     offset: Option<String>,
-    iterator: std::vec::IntoIter<T>,
+    iterator: std::vec::IntoIter<SRecord<T>>,
+    remaining: Option<usize>,
+    invalid_records: Vec<InvalidRecord>,
     query_builder: QueryBuilder<'base, T>,
+    // Set when the base caches queries.
+    cache_key: Option<String>,
+    // Pages served from the query cache instead of fetched.
+    replay: Option<std::vec::IntoIter<String>>,
+    // Page bodies fetched so far, for the query cache.
+    recorded: Vec<String>,
+    // The record `peek` looked at, yielded next.
+    peeked: Option<T>,
 }
 
 impl<'base, T> Iterator for Paginator<'base, T>
@@ -56,99 +271,624 @@ where
     T: Record,
 {
     type Item = T;
-    // This somewhat masks errors..
+    // This somewhat masks errors.. use `QueryBuilder::collect_all` to see them.
     fn next(&mut self) -> Option<Self::Item> {
-        let next = self.iterator.next();
-        if next.is_some() {
-            return next;
+        self.try_next().ok().flatten()
+    }
+}
+
+impl<'base, T> Paginator<'base, T>
+where
+    for<'de> T: Deserialize<'de>,
+    T: Record,
+{
+    fn try_next(&mut self) -> Result<Option<T>> {
+        if let Some(record) = self.peeked.take() {
+            return Ok(Some(record));
         }
+        self.check_params()?;
+        loop {
+            if let Some(record) = self.next_buffered()? {
+                return Ok(Some(record));
+            }
 
-        if self.offset.is_none() {
-            return None;
+            // Once `max_records` have been yielded there's nothing left to fetch, even if the
+            // last page came with an offset.
+            if self.remaining == Some(0) || !self.fetch_next_page()? {
+                return Ok(None);
+            }
         }
+    }
 
-        let url = &format!("{}/{}/{}", URL, self.base.app_key, self.base.table);
-        let mut req = ureq::get(&url);
+    // Checks the query before the first page is fetched, which is while the offset is empty.
+    fn check_params(&self) -> Result<()> {
+        if self.offset.as_deref() == Some("") {
+            self.query_builder.params.check()?;
+        }
+        Ok(())
+    }
 
-        if self.offset.is_some() {
-            req = req.query("offset", self.offset.as_ref().unwrap());
+    // Yields the next record of the page already fetched, without fetching another.
+    fn next_buffered(&mut self) -> Result<Option<T>> {
+        if self.remaining == Some(0) {
+            return Ok(None);
         }
 
-        if self.query_builder.view.is_some() {
-            req = req.query("view", self.query_builder.view.as_ref().unwrap());
+        for record in self.iterator.by_ref() {
+            let record = record.into_record();
+            if let Some(ref validate) = self.query_builder.validator {
+                if let Err(err) = validate(&record) {
+                    let id = record.id().to_string();
+                    if !self.query_builder.skip_invalid {
+                        return Err(err.context(format!("record {} failed validation", id)));
+                    }
+                    warn!("Skipping record {} that failed validation: {}", id, err);
+                    self.invalid_records.push(InvalidRecord {
+                        id,
+                        error: err.to_string(),
+                    });
+                    continue;
+                }
+            }
+
+            if let Some(ref mut remaining) = self.remaining {
+                *remaining -= 1;
+            }
+            return Ok(Some(record));
         }
 
-        if self.query_builder.formula.is_some() {
-            req = req.query(
-                "filterByFormula",
-                self.query_builder.formula.as_ref().unwrap(),
-            );
+        Ok(None)
+    }
+
+    // Replaces the buffered records with the next page. Returns false once the last page has
+    // already been fetched.
+    fn fetch_next_page(&mut self) -> Result<bool> {
+        let offset = match self.offset {
+            Some(ref offset) => offset,
+            None => return Ok(false),
+        };
+
+        if let Some(ref cancel) = self.query_builder.cancel {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled.into());
+            }
         }
 
-        if self.query_builder.sort.is_some() {
-            for (i, ref sort) in self.query_builder.sort.as_ref().unwrap().iter().enumerate() {
-                req = req.query(&format!("sort[{}][field]", i), &sort.0);
-                req = req.query(&format!("sort[{}][direction]", i), &sort.1.to_string());
+        if offset.is_empty() {
+            if let (Some(cache), Some(key)) = (&self.base.query_cache, &self.cache_key) {
+                let cached = cache.lock().unwrap().get(key);
+                self.replay = cached.map(|pages| Vec::clone(&pages).into_iter());
             }
         }
 
-        debug!("Blocking on get!");
-        let results: RecordPage<T> = req
-            .set("Authorization", &format!("Bearer {}", &self.base.api_key))
-            .set("Content-Type", "application/json")
-            .call()
-            .ok()?
-            .into_json()
-            .ok()?;
+        let body = match self.replay.as_mut().and_then(Iterator::next) {
+            Some(body) => body,
+            None => {
+                let url = self.base.records_endpoint();
+                let mut req = Request::new("GET", &url).query("offset", offset);
+
+                req = apply_query_params(req, &self.query_builder.params);
+
+                debug!("Blocking on get!");
+                let response = self.base.send_timed(req)?;
+                if self.query_builder.adaptive_page_size {
+                    self.adapt_page_size(response.elapsed - response.throttled);
+                }
+                let body = response.value.body;
+                if self.cache_key.is_some() {
+                    self.recorded.push(body.clone());
+                }
+                body
+            }
+        };
+        let results: RecordPage<T> = if self.query_builder.skip_invalid
+            || self.query_builder.empty_fields_as_null
+            || self.query_builder.unknown_fields != UnknownFields::Ignore
+        {
+            self.parse_each(serde_json::from_str(&body)?)?
+        } else {
+            serde_json::from_str(&body)?
+        };
 
-        if results.offset.is_empty() {
-            self.offset = None;
+        let (records, offset) = results.into_parts();
+        self.offset = offset;
+        self.iterator = records.into_iter();
+        if self.offset.is_none() && self.replay.is_none() {
+            self.cache_pages();
+        }
+        Ok(true)
+    }
+
+    // Picks the next page's size for `QueryBuilder::adaptive_page_size`, from how long Airtable
+    // took over the last one, not counting any wait for the rate limiter.
+    fn adapt_page_size(&mut self, elapsed: Duration) {
+        let near_limit = self.base.rate_limit_state().is_some_and(|state| {
+            state.tokens_available < 1.0 || state.server_remaining.is_some_and(|left| left <= 1)
+        });
+        let size = self.query_builder.params.page_size.unwrap_or(MAX_PAGE_SIZE);
+        let size = if near_limit {
+            MAX_PAGE_SIZE
+        } else if elapsed > SLOW_PAGE {
+            (size / 2).max(ADAPTIVE_MIN_PAGE_SIZE)
         } else {
-            self.offset = Some(results.offset);
+            (size * 2).min(MAX_PAGE_SIZE)
+        };
+        self.query_builder.params.page_size = Some(size);
+    }
+
+    // Stores the pages of a query that was fetched all the way through.
+    fn cache_pages(&mut self) {
+        if let (Some(cache), Some(key)) = (&self.base.query_cache, &self.cache_key) {
+            let pages = Arc::new(std::mem::take(&mut self.recorded));
+            cache.lock().unwrap().insert(key, pages);
+        }
+    }
+
+    // Deserializes each record on its own, checking for unknown fields and, when skipping
+    // invalid records, setting aside the ones that fail.
+    fn parse_each(&mut self, page: RecordPage<serde_json::Value>) -> Result<RecordPage<T>> {
+        let mut records = Vec::with_capacity(page.records.len());
+
+        for mut record in page.records {
+            self.check_unknown_fields(&record)?;
+            if self.query_builder.empty_fields_as_null {
+                if let (Some(fields), Some(selected)) = (
+                    record.fields.as_object_mut(),
+                    &self.query_builder.params.fields,
+                ) {
+                    for field in selected {
+                        fields
+                            .entry(field.as_str())
+                            .or_insert(serde_json::Value::Null);
+                    }
+                }
+            }
+            match serde_json::from_value(record.fields) {
+                Ok(fields) => records.push(SRecord {
+                    id: record.id,
+                    fields,
+                    created_time: record.created_time,
+                    comment_count: record.comment_count,
+                }),
+                Err(err) if self.query_builder.skip_invalid => {
+                    warn!(
+                        "Skipping record {} that failed to deserialize: {}",
+                        record.id, err
+                    );
+                    self.invalid_records.push(InvalidRecord {
+                        id: record.id,
+                        error: err.to_string(),
+                    });
+                }
+                Err(err) => return Err(err.into()),
+            }
         }
 
-        let window: Vec<T> = results
-            .records
+        Ok(RecordPage {
+            records,
+            offset: page.offset,
+        })
+    }
+
+    fn check_unknown_fields(&self, record: &SRecord<serde_json::Value>) -> Result<()> {
+        let known = T::field_names();
+        if self.query_builder.unknown_fields == UnknownFields::Ignore || known.is_empty() {
+            return Ok(());
+        }
+        let unknown: Vec<String> = record
+            .fields
+            .as_object()
             .into_iter()
-            .map(|record| {
-                let mut record_t: T = record.fields;
-                record_t.set_id(record.id);
-                record_t
-            })
+            .flat_map(|fields| fields.keys())
+            .filter(|name| !known.contains(&name.as_str()))
+            .cloned()
             .collect();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+
+        if self.query_builder.unknown_fields == UnknownFields::Error {
+            return Err(Error::UnknownFields {
+                id: record.id.clone(),
+                fields: unknown,
+            }
+            .into());
+        }
+        warn!(
+            "Record {} has fields the record type doesn't know about, has the table changed? {:?}",
+            record.id, unknown
+        );
+        Ok(())
+    }
+
+    /// The records skipped so far because they failed to deserialize, with
+    /// `QueryBuilder::skip_invalid_records`.
+    pub fn invalid_records(&self) -> &[InvalidRecord] {
+        &self.invalid_records
+    }
+
+    /// The record `next` will yield, without consuming it, e.g. to group consecutive records
+    /// by a field in one pass. At the end of a page this fetches the next one. Like `next`, a
+    /// failing page reads as the end of the records.
+    pub fn peek(&mut self) -> Option<&T> {
+        if self.peeked.is_none() {
+            self.peeked = self.try_next().ok().flatten();
+        }
+        self.peeked.as_ref()
+    }
+
+    /// Yields each record along with its id, e.g. to build a map from id to record.
+    pub fn with_ids(self) -> WithIds<'base, T> {
+        WithIds { paginator: self }
+    }
+}
+
+/// Iterates over `(id, record)` pairs, as returned by `Paginator::with_ids`.
+pub struct WithIds<'base, T: Record> {
+    paginator: Paginator<'base, T>,
+}
+
+impl<'base, T> Iterator for WithIds<'base, T>
+where
+    for<'de> T: Deserialize<'de>,
+    T: Record,
+{
+    type Item = (RecordId, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = self.paginator.next()?;
+        Some((record.id().clone(), record))
+    }
+}
+
+/// Iterates over the pages of a query, as returned by `QueryBuilder::pages`. Ends after the
+/// first page that fails.
+pub struct Pages<'base, T: Record> {
+    paginator: Paginator<'base, T>,
+    failed: bool,
+}
+
+impl<'base, T> Iterator for Pages<'base, T>
+where
+    for<'de> T: Deserialize<'de>,
+    T: Record,
+{
+    type Item = Result<Vec<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        if let Err(err) = self.paginator.check_params() {
+            self.failed = true;
+            return Some(Err(err));
+        }
+        if self.paginator.remaining == Some(0) {
+            return None;
+        }
 
-        self.iterator = window.into_iter();
-        self.iterator.next()
+        let page = match self.paginator.fetch_next_page() {
+            Ok(false) => return None,
+            Ok(true) => std::iter::from_fn(|| self.paginator.next_buffered().transpose()).collect(),
+            Err(err) => Err(err),
+        };
+        self.failed = page.is_err();
+        Some(page)
     }
 }
 
+/// A record that was skipped because it didn't deserialize into the record type, or was
+/// rejected by `QueryBuilder::validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidRecord {
+    pub id: String,
+    pub error: String,
+}
+
 pub trait Record {
-    fn set_id(&mut self, error: String);
-    fn id(&self) -> &str;
+    fn set_id(&mut self, id: RecordId);
+    /// Empty for a record that hasn't been created yet.
+    fn id(&self) -> &RecordId;
+
+    /// Receives the record's `createdTime` metadata. Ignored unless overridden.
+    fn set_created_time(&mut self, _created_time: String) {}
+
+    /// Receives the record's comment count, which Airtable only sends for queries made with
+    /// `QueryBuilder::comment_count`. Ignored unless overridden.
+    fn set_comment_count(&mut self, _comment_count: u64) {}
+
+    /// The Airtable names of the record's fields, for code that works over any record type,
+    /// e.g. to check them against a table's schema. Empty unless overridden.
+    fn field_names() -> &'static [&'static str]
+    where
+        Self: Sized,
+    {
+        &[]
+    }
+}
+
+/// Implements [`Record`] for structs with an `id: RecordId` field, e.g. `impl_record!(Word);`.
+/// List the struct's field names after `=>` to implement `Record::field_names` too, e.g.
+/// `impl_record!(Word => ["Word", "Google"]);`.
+#[macro_export]
+macro_rules! impl_record {
+    ($ty:ty => [$($field:expr),* $(,)?]) => {
+        impl $crate::Record for $ty {
+            fn set_id(&mut self, id: $crate::RecordId) {
+                self.id = id;
+            }
+
+            fn id(&self) -> &$crate::RecordId {
+                &self.id
+            }
+
+            fn field_names() -> &'static [&'static str] {
+                &[$($field),*]
+            }
+        }
+    };
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl $crate::Record for $ty {
+                fn set_id(&mut self, id: $crate::RecordId) {
+                    self.id = id;
+                }
+
+                fn id(&self) -> &$crate::RecordId {
+                    &self.id
+                }
+            }
+        )+
+    };
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SortDirection {
     Descending,
     Ascending,
 }
 
-impl ToString for SortDirection {
-    fn to_string(&self) -> String {
+impl fmt::Display for SortDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortDirection::Descending => f.write_str("desc"),
+            SortDirection::Ascending => f.write_str("asc"),
+        }
+    }
+}
+
+/// How Airtable renders cell values, see [`FormatOptions`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellFormat {
+    /// Values in their JSON form, e.g. numbers as numbers. The default.
+    Json,
+    /// Values as the UI displays them, e.g. `"$1,000.00"`, which needs a time zone and locale.
+    String,
+}
+
+impl fmt::Display for CellFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            SortDirection::Descending => String::from("desc"),
-            SortDirection::Ascending => String::from("asc"),
+            CellFormat::Json => f.write_str("json"),
+            CellFormat::String => f.write_str("string"),
+        }
+    }
+}
+
+/// How cell values are formatted in responses, for queries (`QueryBuilder::format`) and single
+/// records (`Base::get_formatted`) alike. Airtable requires `time_zone` and `user_locale` with
+/// `CellFormat::String`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    pub cell_format: CellFormat,
+    /// e.g. `"Europe/London"`.
+    pub time_zone: Option<String>,
+    /// e.g. `"en-gb"`.
+    pub user_locale: Option<String>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            cell_format: CellFormat::Json,
+            time_zone: None,
+            user_locale: None,
+        }
+    }
+}
+
+impl FormatOptions {
+    fn check(&self) -> Result<()> {
+        if self.cell_format == CellFormat::String
+            && (self.time_zone.is_none() || self.user_locale.is_none())
+        {
+            return Err(invalid_query(
+                "cell format string needs both a time zone and a user locale",
+            ));
+        }
+        Ok(())
+    }
+
+    fn apply(&self, mut req: Request) -> Request {
+        req = req.query("cellFormat", &self.cell_format.to_string());
+        if let Some(ref time_zone) = self.time_zone {
+            req = req.query("timeZone", time_zone);
+        }
+        if let Some(ref user_locale) = self.user_locale {
+            req = req.query("userLocale", user_locale);
         }
+        req
     }
 }
 
+/// What a query does with fields Airtable returns that aren't in `Record::field_names`, which
+/// usually means a column was added or renamed. See `QueryBuilder::unknown_fields`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnknownFields {
+    /// Drop them silently, like serde does. The default.
+    Ignore,
+    /// Log a warning for each record that has any.
+    Warn,
+    /// Fail with `Error::UnknownFields`.
+    Error,
+}
+
+/// What `QueryBuilder::collect_map` does when two records have the same key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DuplicateKeys {
+    /// Fail with `Error::DuplicateKey`.
+    Error,
+    /// Keep the record that came later in the query.
+    LastWins,
+}
+
+type Validator<'base, T> = Arc<dyn Fn(&T) -> Result<()> + Send + Sync + 'base>;
+
 pub struct QueryBuilder<'base, T: Record> {
     base: &'base Base<T>,
+    params: QueryParams,
+    cancel: Option<CancellationToken>,
+    skip_invalid: bool,
+    empty_fields_as_null: bool,
+    adaptive_page_size: bool,
+    unknown_fields: UnknownFields,
+    validator: Option<Validator<'base, T>>,
+}
+
+// Derived `Clone` would needlessly require `T: Clone`; only the reference is shared.
+impl<'base, T: Record> Clone for QueryBuilder<'base, T> {
+    fn clone(&self) -> Self {
+        QueryBuilder {
+            base: self.base,
+            params: self.params.clone(),
+            cancel: self.cancel.clone(),
+            skip_invalid: self.skip_invalid,
+            empty_fields_as_null: self.empty_fields_as_null,
+            adaptive_page_size: self.adaptive_page_size,
+            unknown_fields: self.unknown_fields,
+            validator: self.validator.clone(),
+        }
+    }
+}
 
+// Everything a read sends besides the offset. Kept apart from the builder so every read path
+// serializes it through `apply_query_params` rather than its own copy of the logic.
+#[derive(Default, Clone)]
+struct QueryParams {
     view: Option<String>,
     formula: Option<String>,
+    // Why `formula_args` couldn't set the formula, reported by `check`.
+    formula_error: Option<Error>,
 
     // TODO: Second value here should be an enum.
     sort: Option<Vec<(String, SortDirection)>>,
+
+    comment_count: bool,
+    page_size: Option<usize>,
+    max_records: Option<usize>,
+    fields: Option<Vec<String>>,
+    return_fields_by_field_id: bool,
+    format: Option<FormatOptions>,
+}
+
+impl QueryParams {
+    // Catches mistakes Airtable would either ignore or only reject with an opaque 422, before
+    // anything is sent.
+    fn check(&self) -> Result<()> {
+        if let Some(ref err) = self.formula_error {
+            return Err(err.clone().into());
+        }
+        if let Some(page_size) = self.page_size {
+            if page_size == 0 || page_size > 100 {
+                return Err(invalid_query(format!(
+                    "page size must be between 1 and 100, not {}",
+                    page_size
+                )));
+            }
+        }
+        if self.max_records == Some(0) {
+            return Err(invalid_query("max records must be at least 1"));
+        }
+        if self
+            .sort
+            .iter()
+            .flatten()
+            .any(|(field, _)| field.trim().is_empty())
+        {
+            return Err(invalid_query("sort field names can't be empty"));
+        }
+        match self.format {
+            Some(ref format) => format.check(),
+            None => Ok(()),
+        }
+    }
+}
+
+// Every parameter the query sends, in the order it sends them.
+fn query_cache_key(params: &QueryParams) -> String {
+    let req = apply_query_params(Request::new("GET", ""), params);
+    let pairs: Vec<String> = req
+        .query
+        .iter()
+        .map(|(param, value)| format!("{}={}", param, value))
+        .collect();
+    pairs.join("&")
+}
+
+fn invalid_query(reason: impl Into<String>) -> anyhow::Error {
+    Error::InvalidQuery {
+        reason: reason.into(),
+    }
+    .into()
+}
+
+fn apply_query_params(mut req: Request, params: &QueryParams) -> Request {
+    if let Some(ref view) = params.view {
+        req = req.query("view", view);
+    }
+
+    if let Some(ref formula) = params.formula {
+        req = req.query("filterByFormula", formula);
+    }
+
+    if let Some(ref sort) = params.sort {
+        for (i, sort) in sort.iter().enumerate() {
+            req = req.query(&format!("sort[{}][field]", i), &sort.0);
+            req = req.query(&format!("sort[{}][direction]", i), &sort.1.to_string());
+        }
+    }
+
+    if let Some(page_size) = params.page_size {
+        req = req.query("pageSize", &page_size.to_string());
+    }
+
+    if let Some(max_records) = params.max_records {
+        req = req.query("maxRecords", &max_records.to_string());
+    }
+
+    if params.comment_count {
+        req = req.query("recordMetadata[]", "commentCount");
+    }
+
+    if let Some(ref format) = params.format {
+        req = format.apply(req);
+    }
+
+    if params.return_fields_by_field_id {
+        req = req.query("returnFieldsByFieldId", "true");
+    }
+
+    if let Some(ref fields) = params.fields {
+        // An empty selection still has to be sent, or every field comes back.
+        if fields.is_empty() {
+            req = req.query("fields[]", "");
+        }
+        for field in fields {
+            req = req.query("fields[]", field);
+        }
+    }
+
+    req
 }
 
 impl<'base, T> QueryBuilder<'base, T>
@@ -156,20 +896,81 @@ where
     for<'de> T: Deserialize<'de>,
     T: Record,
 {
-    pub fn view(mut self, view: &str) -> Self {
-        self.view = Some(view.to_owned());
+    /// Only returns records visible in the view, in the order the view sorts them in unless
+    /// `sort` is also set, which overrides the view's order.
+    pub fn view(mut self, view: impl Into<String>) -> Self {
+        self.params.view = Some(view.into());
+        self
+    }
+
+    /// Only returns records for which the formula is truthy. Combined with `view`, the formula
+    /// filters the records within the view rather than across the whole table.
+    ///
+    /// Takes anything convertible into a `String`, so a formula built at runtime is moved in
+    /// rather than copied.
+    pub fn formula(mut self, formula: impl Into<String>) -> Self {
+        self.params.formula = Some(formula.into());
+        self.params.formula_error = None;
         self
     }
 
-    pub fn formula(mut self, formula: &str) -> Self {
-        self.formula = Some(formula.to_owned());
+    /// Narrows the formula set so far to records that also match `formula`, combining the two
+    /// as `AND(existing, formula)`, or sets it if there's none yet. For filters built up from
+    /// optional parts, e.g. search options a user may or may not have filled in.
+    pub fn and_formula(self, formula: impl Into<String>) -> Self {
+        self.combine_formula("AND", formula.into())
+    }
+
+    /// Like `and_formula`, but widens the formula set so far with `OR(existing, formula)`.
+    pub fn or_formula(self, formula: impl Into<String>) -> Self {
+        self.combine_formula("OR", formula.into())
+    }
+
+    // Each formula is a whole argument of the function, so neither needs parenthesizing.
+    fn combine_formula(mut self, function: &str, formula: String) -> Self {
+        self.params.formula = Some(match self.params.formula.take() {
+            Some(existing) => format!("{}({}, {})", function, existing, formula),
+            None => formula,
+        });
         self
     }
 
+    /// Sets the formula from a template, binding each `?` to an escaped argument. See
+    /// [`formula::bind`]. A template that can't be bound fails the query before anything is
+    /// sent.
+    pub fn formula_args(mut self, template: &str, args: &[formula::FormulaValue]) -> Self {
+        match formula::try_bind(template, args) {
+            Ok(formula) => self.formula(formula),
+            Err(err) => {
+                self.params.formula_error = Some(err);
+                self
+            }
+        }
+    }
+
+    /// Only fetches records modified after `since`, according to the table's "Last modified
+    /// time" field named `field`. Replaces any formula set so far. See
+    /// [`formula::modified_since`].
+    pub fn modified_since(self, field: &str, since: SystemTime) -> Self {
+        self.formula(formula::modified_since(field, since))
+    }
+
+    /// Only fetches records whose date field `field` is after `start` and before `end`.
+    /// Replaces any formula set so far. See [`formula::between_dates`].
+    pub fn between_dates(self, field: &str, start: SystemTime, end: SystemTime) -> Self {
+        self.formula(formula::between_dates(field, start, end))
+    }
+
+    /// Sorts by `field`. Calling it again adds a tie-breaking sort on another field. Setting any
+    /// sort replaces the order of the `view`, if there is one.
+    ///
+    /// Any field can be sorted on, computed ones such as formula, rollup and lookup fields
+    /// included, which sort by their result's type. Pass the name as shown in Airtable, spaces
+    /// and all; it's encoded when the request is sent.
     pub fn sort(mut self, field: &str, direction: SortDirection) -> Self {
-        match self.sort {
+        match self.params.sort {
             None => {
-                self.sort = Some(vec![(field.to_owned(), direction)]);
+                self.params.sort = Some(vec![(field.to_owned(), direction)]);
             }
             Some(ref mut sort) => {
                 let tuple = (field.to_owned(), direction);
@@ -178,82 +979,1439 @@ where
         };
         self
     }
-}
 
-impl<'base, T> IntoIterator for QueryBuilder<'base, T>
-where
-    for<'de> T: Deserialize<'de>,
-    T: Record,
-{
-    type Item = T;
-    type IntoIter = Paginator<'base, T>;
+    /// Only transfers the listed fields. The fields left out must deserialize to something,
+    /// e.g. through `#[serde(default)]`.
+    pub fn fields(mut self, fields: &[&str]) -> Self {
+        self.params.fields = Some(fields.iter().map(|&field| field.to_owned()).collect());
+        self
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        Paginator {
-            base: &self.base,
-            offset: Some("".to_owned()),
-            iterator: vec![].into_iter(),
-            query_builder: self,
-        }
+    /// Like `fields`, but selects the fields by id (`fld...`), so the query keeps working when
+    /// a column is renamed. You generally want `return_fields_by_field_id` as well, so the
+    /// response is keyed the same way.
+    pub fn fields_by_id(self, field_ids: &[&str]) -> Self {
+        self.fields(field_ids)
     }
-}
 
-impl<T> Base<T>
-where
-    for<'de> T: Deserialize<'de>,
-    T: Record,
-{
-    pub fn query(&self) -> QueryBuilder<T> {
-        QueryBuilder {
-            base: self,
-            view: None,
-            formula: None,
-            sort: None,
-        }
+    /// Sets how cell values are formatted. The record type has to match, e.g. with
+    /// `CellFormat::String` every field must deserialize from a string.
+    pub fn format(mut self, format: FormatOptions) -> Self {
+        self.params.format = Some(format);
+        self
     }
 
-    pub async fn create(&self, record: &T) -> Result<()>
-    where
-        T: serde::Serialize,
+    /// Asks Airtable to key each record's fields by field id rather than name. The record type
+    /// must then name its fields by id too, e.g. `#[serde(rename = "fldXXXXXXXXXXXXXX")]`.
+    pub fn return_fields_by_field_id(mut self) -> Self {
+        self.params.return_fields_by_field_id = true;
+        self
+    }
+
+    /// Fetches the ids of every matching record and none of their fields, which is much cheaper
+    /// than fetching the records when only the ids are needed.
+    pub fn record_ids(self) -> Result<Vec<String>> {
+        self.base.record_ids(&self.params)
+    }
+
+    /// Fetches every value of `field` across the matching records, without duplicates and in
+    /// the order they're first seen, e.g. for a "filter by" dropdown. Only that one field is
+    /// asked for. The values of multi-value fields such as multiple selects are listed one by
+    /// one; strings come back as they are and other values as their JSON, e.g. `42` or `true`.
+    pub fn distinct_values(self, field: &str) -> Result<Vec<String>> {
+        self.base.distinct_values_in(field, &self.params)
+    }
+
+    /// Counts the matching records, fetching only their ids. Airtable has no count endpoint, so
+    /// this still pages through every match.
+    pub fn count(self) -> Result<usize> {
+        Ok(self.record_ids()?.len())
+    }
+
+    /// Counts the matching records, stopping at `n`, so the result is the smaller of `n` and the
+    /// real count, e.g. for a "99+" badge. Asks for at most `n` ids, a page per hundred.
+    /// Overrides `max_records`.
+    pub fn count_up_to(self, n: usize) -> Result<usize> {
+        if n == 0 {
+            return Ok(0);
+        }
+        self.max_records(n).count()
+    }
+
+    /// Removes the view set so far, e.g. from a clone of a template query.
+    pub fn view_clear(mut self) -> Self {
+        self.params.view = None;
+        self
+    }
+
+    /// Removes every sort set so far, leaving the view's order (or the table's) in place.
+    pub fn sort_clear(mut self) -> Self {
+        self.params.sort = None;
+        self
+    }
+
+    /// How many records each page holds, up to Airtable's maximum (and default) of 100.
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.params.page_size = Some(page_size);
+        self
+    }
+
+    /// Stops after this many records in total. No further page is requested once they've all
+    /// been yielded, even if Airtable returned an offset with the last one.
+    pub fn max_records(mut self, max_records: usize) -> Self {
+        self.params.max_records = Some(max_records);
+        self
+    }
+
+    /// Asks Airtable to include each record's comment count, handed to
+    /// `Record::set_comment_count`.
+    pub fn comment_count(mut self) -> Self {
+        self.params.comment_count = true;
+        self
+    }
+
+    /// Stops paging once `token` is cancelled. The page in flight, if any, still completes;
+    /// the iterator then ends and `collect_all` fails with `Error::Cancelled`. Dropping the
+    /// iterator part way through is always fine too.
+    pub fn cancel_on(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Skips records that fail to deserialize instead of failing their whole page, logging a
+    /// warning for each. The skipped records are listed by `Paginator::invalid_records`, or
+    /// returned by `collect_valid`. By default one bad record fails the page.
+    pub fn skip_invalid_records(mut self) -> Self {
+        self.skip_invalid = true;
+        self
+    }
+
+    /// With `fields`, fills in `null` for each selected field a record doesn't have. Airtable
+    /// leaves empty fields out of responses, so without this an empty field and one that wasn't
+    /// selected look the same. Pair it with [`fields::Selected`] to tell them apart; every
+    /// selected field then has to accept `null`, as `Option` and `Selected` do.
+    pub fn empty_fields_as_null(mut self) -> Self {
+        self.empty_fields_as_null = true;
+        self
+    }
+
+    /// Adjusts the page size from one page to the next, starting at `page_size` (or 25) so the
+    /// first records arrive quickly. Each page that comes back within a second doubles the size
+    /// of the next, up to Airtable's maximum of 100, and each slower one halves it, down to 10,
+    /// keeping big pages from running into timeouts. When the rate limiter has no request to
+    /// spare, or Airtable reports at most one left, the next page is as big as it gets: every
+    /// request is then worth the most records it can bring.
+    pub fn adaptive_page_size(mut self) -> Self {
+        self.params
+            .page_size
+            .get_or_insert(ADAPTIVE_FIRST_PAGE_SIZE);
+        self.adaptive_page_size = true;
+        self
+    }
+
+    /// Detects schema drift by checking each record's fields against `Record::field_names`,
+    /// which must be implemented for this to do anything (e.g. with `impl_record!(T => [...])`).
+    /// For a compile-time alternative that always errors, put `#[serde(deny_unknown_fields)]`
+    /// on the record type.
+    pub fn unknown_fields(mut self, unknown_fields: UnknownFields) -> Self {
+        self.unknown_fields = unknown_fields;
+        self
+    }
+
+    /// Checks every record with `validate` before it's yielded, e.g. that a required field
+    /// isn't empty. A record it rejects fails the query like one that didn't deserialize, or is
+    /// skipped and listed with the invalid records under `skip_invalid_records`.
+    pub fn validate(mut self, validate: impl Fn(&T) -> Result<()> + Send + Sync + 'base) -> Self {
+        self.validator = Some(Arc::new(validate));
+        self
+    }
+
+    /// Fetches every page, returning the records that deserialized (and passed `validate`, if
+    /// set) along with the ones that didn't. Fails on anything other than an invalid record.
+    pub fn collect_valid(self) -> Result<(Vec<T>, Vec<InvalidRecord>)> {
+        let mut paginator = self.skip_invalid_records().into_iter();
+        let mut records = vec![];
+
+        while let Some(record) = paginator.try_next()? {
+            records.push(record);
+        }
+
+        Ok((records, paginator.invalid_records))
+    }
+
+    /// Fetches every page into a map keyed by `key`, e.g. a unique business key, to load a
+    /// lookup table. `duplicates` decides what happens when two records share a key. Fails on
+    /// the first error, like `collect_all`.
+    pub fn collect_map<K>(
+        self,
+        key: impl Fn(&T) -> K,
+        duplicates: DuplicateKeys,
+    ) -> Result<HashMap<K, T>>
+    where
+        K: Eq + std::hash::Hash + fmt::Debug,
     {
-        let url = format!("{}/{}/{}", URL, self.app_key, self.table);
+        let mut paginator = self.into_iter();
+        let mut records = HashMap::new();
 
-        let serializing_record = SRecord {
-            id: String::new(),
-            fields: record,
+        while let Some(record) = paginator.try_next()? {
+            let id = record.id().to_string();
+            if let Some(previous) = records.insert(key(&record), record) {
+                if duplicates == DuplicateKeys::Error {
+                    return Err(Error::DuplicateKey {
+                        key: format!("{:?}", key(&previous)),
+                        ids: vec![previous.id().to_string(), id],
+                    }
+                    .into());
+                }
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Fetches every page and groups the records by `key`, e.g. a status field, like grouping
+    /// a view in the Airtable UI. Each group keeps the records in the order they came back.
+    /// Fails on the first error, like `collect_all`.
+    pub fn group_by<K>(self, key: impl Fn(&T) -> K) -> Result<HashMap<K, Vec<T>>>
+    where
+        K: Eq + std::hash::Hash,
+    {
+        let mut paginator = self.into_iter();
+        let mut groups: HashMap<K, Vec<T>> = HashMap::new();
+
+        while let Some(record) = paginator.try_next()? {
+            groups.entry(key(&record)).or_default().push(record);
+        }
+
+        Ok(groups)
+    }
+
+    /// Fetches every page, returning the first error instead of stopping silently like the
+    /// iterator does.
+    pub fn collect_all(self) -> Result<Vec<T>> {
+        let mut paginator = self.into_iter();
+        let mut records = vec![];
+
+        while let Some(record) = paginator.try_next()? {
+            records.push(record);
+        }
+
+        Ok(records)
+    }
+
+    /// Fetches the single page starting at `offset` (`None` for the first page) and returns its
+    /// records along with the offset of the next page, or `None` after the last one. Airtable's
+    /// raw paging primitive, for paging the iterator can't do, e.g. checkpointing the offset
+    /// between runs. Airtable expires offsets after a few minutes.
+    ///
+    /// Validation and the options for invalid records apply as usual; `max_records` and the
+    /// query cache don't span calls.
+    pub fn fetch_page(&self, offset: Option<&str>) -> Result<(Vec<T>, Option<String>)> {
+        let mut paginator = self.clone().into_iter();
+        paginator.check_params()?;
+        paginator.offset = Some(offset.unwrap_or_default().to_owned());
+        paginator.cache_key = None;
+
+        let mut records = vec![];
+        if paginator.fetch_next_page()? {
+            while let Some(record) = paginator.next_buffered()? {
+                records.push(record);
+            }
+        }
+        Ok((records, paginator.offset))
+    }
+
+    /// Fetches a page at a time, yielding each one whole rather than record by record, e.g. to
+    /// write each page to a database in one go.
+    pub fn pages(self) -> Pages<'base, T> {
+        Pages {
+            paginator: self.into_iter(),
+            failed: false,
+        }
+    }
+
+    /// Fetches up to `limit` records and whether there are more beyond them, e.g. for a "load
+    /// more" button. Cheaper than counting: it asks for one record more than `limit` and
+    /// reports whether it came back. Overrides `max_records`.
+    pub fn collect_up_to(self, limit: usize) -> Result<(Vec<T>, bool)> {
+        let mut records = self.max_records(limit.saturating_add(1)).collect_all()?;
+        let more = records.len() > limit;
+        records.truncate(limit);
+        Ok((records, more))
+    }
+
+    /// Sorts by the record's creation time, read from `created_field`, a "Created time" field.
+    /// Airtable doesn't give tables one; someone has to add it, under whatever name they like.
+    /// The API can't sort on `CREATED_TIME()` directly, and a table without the field fails the
+    /// query with a 422.
+    pub fn sort_by_created(self, created_field: &str, direction: SortDirection) -> Self {
+        self.sort(created_field, direction)
+    }
+
+    /// Newest records first, by the "Created time" field `created_field` as in
+    /// `sort_by_created`. With `max_records`, fetches just the latest few without paging
+    /// through the table.
+    pub fn newest_first_by(self, created_field: &str) -> Self {
+        self.sort_by_created(created_field, SortDirection::Descending)
+    }
+
+    /// Shorthand for `newest_first_by(formula::CREATED_FIELD)`, for tables whose "Created time"
+    /// field is named exactly `Created`. Fails with a 422 on tables without one.
+    pub fn newest_first(self) -> Self {
+        self.newest_first_by(formula::CREATED_FIELD)
+    }
+}
+
+impl<'base, T> IntoIterator for QueryBuilder<'base, T>
+where
+    for<'de> T: Deserialize<'de>,
+    T: Record,
+{
+    type Item = T;
+    type IntoIter = Paginator<'base, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Paginator {
+            base: self.base,
+            offset: Some("".to_owned()),
+            iterator: vec![].into_iter(),
+            remaining: self.params.max_records,
+            invalid_records: vec![],
+            cache_key: self
+                .base
+                .query_cache
+                .as_ref()
+                .map(|_| query_cache_key(&self.params)),
+            replay: None,
+            recorded: vec![],
+            peeked: None,
+            query_builder: self,
+        }
+    }
+}
+
+/// Runs several queries concurrently, one thread each, and collects every result under the
+/// label it was given. A failing query only fails its own entry.
+///
+/// Bases that should share Airtable's rate limit (e.g. several tables of one base) must be
+/// given clones of the same `RateLimiter`.
+pub fn fetch_tables<'base, T>(
+    queries: Vec<(&str, QueryBuilder<'base, T>)>,
+) -> HashMap<String, Result<Vec<T>>>
+where
+    for<'de> T: Deserialize<'de>,
+    T: Record + Send + Sync,
+{
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = queries
+            .into_iter()
+            .map(|(label, query)| (label, scope.spawn(move || query.collect_all())))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|(label, handle)| {
+                let result = handle
+                    .join()
+                    .unwrap_or_else(|_| Err(anyhow!("query for {} panicked", label)));
+                (label.to_owned(), result)
+            })
+            .collect()
+    })
+}
+
+/// The fields that differ between two versions of a record, as a JSON object of field names to
+/// their values in `new`, ready for `Base::update_batch_partial`. A field `new` leaves out (e.g.
+/// a `None` skipped when serializing) but `old` had maps to `null`, which clears it.
+///
+/// Fields are compared as they serialize, so the id and anything else skipped when
+/// serializing never counts as a change.
+///
+/// ```
+/// # use serde::Serialize;
+/// # #[derive(Serialize, Default, Clone)]
+/// # struct Word {
+/// #     #[serde(skip_serializing)]
+/// #     id: airtable::RecordId,
+/// #     #[serde(rename = "Word")]
+/// #     word: String,
+/// #     #[serde(rename = "Google")]
+/// #     google: i64,
+/// # }
+/// # airtable::impl_record!(Word);
+/// let old = Word { word: "lurid".into(), google: 6870000, ..Word::default() };
+/// let new = Word { google: 6900000, ..old.clone() };
+/// assert_eq!(airtable::diff(&old, &new)?, serde_json::json!({ "Google": 6900000 }));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn diff<T: Record + Serialize>(old: &T, new: &T) -> Result<serde_json::Value> {
+    let fields = |record: &T| -> Result<serde_json::Map<String, serde_json::Value>> {
+        match serde_json::to_value(record)? {
+            serde_json::Value::Object(fields) => Ok(fields),
+            _ => Err(Error::FieldsNotAnObject {
+                id: record.id().to_string(),
+            }
+            .into()),
+        }
+    };
+    let old = fields(old)?;
+    let new = fields(new)?;
+
+    let mut changed: serde_json::Map<_, _> = new
+        .iter()
+        .filter(|(name, value)| old.get(*name) != Some(*value))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    for name in old.keys().filter(|name| !new.contains_key(*name)) {
+        changed.insert(name.clone(), serde_json::Value::Null);
+    }
+    Ok(serde_json::Value::Object(changed))
+}
+
+fn emptied<V: Clone>(cache: &Arc<Mutex<LruCache<V>>>) -> Arc<Mutex<LruCache<V>>> {
+    Arc::new(Mutex::new(cache.lock().unwrap().emptied()))
+}
+
+fn has_header(headers: &[(String, String)], name: &str) -> bool {
+    headers
+        .iter()
+        .any(|(existing, _)| existing.eq_ignore_ascii_case(name))
+}
+
+// Rebuilt rather than relying on `serde_json::Map`'s order, which is insertion order when
+// another crate enables serde_json's `preserve_order` feature.
+fn sort_keys(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            entries
+                .into_iter()
+                .map(|(key, value)| (key, sort_keys(value)))
+                .collect()
+        }
+        serde_json::Value::Array(values) => values.into_iter().map(sort_keys).collect(),
+        value => value,
+    }
+}
+
+// An empty id would PATCH the table's list endpoint, which Airtable rejects with an unhelpful
+// error.
+fn require_id(id: &str) -> Result<()> {
+    if id.is_empty() {
+        return Err(Error::MissingId.into());
+    }
+    Ok(())
+}
+
+// Airtable's error bodies are either `{"error": "NOT_FOUND"}` or
+// `{"error": {"type": "...", "message": "..."}}`.
+fn error_type(response: &Response) -> Option<String> {
+    let body: serde_json::Value = response.json().ok()?;
+    let error = &body["error"];
+    error["type"]
+        .as_str()
+        .or_else(|| error.as_str())
+        .map(str::to_owned)
+}
+
+// The fields named in a missing-fields error, e.g. `Missing required fields "Name", "Email"`.
+// `None` unless the body is that error.
+fn missing_fields_error(response: &Response) -> Option<Error> {
+    if error_type(response).as_deref() != Some("INVALID_REQUEST_MISSING_FIELDS") {
+        return None;
+    }
+    let body: serde_json::Value = response.json().ok()?;
+    let message = body["error"]["message"].as_str().unwrap_or_default();
+    let fields = message
+        .split('"')
+        .skip(1)
+        .step_by(2)
+        .map(str::to_owned)
+        .collect();
+    Some(Error::MissingRequiredFields { fields })
+}
+
+impl<T: Record> Base<T> {
+    /// Replaces the HTTP transport, e.g. with a fake that returns canned responses in tests.
+    pub fn http_client(mut self, client: impl HttpClient + 'static) -> Self {
+        self.http = Arc::new(client);
+        self
+    }
+
+    /// Switches to a ureq client that honors the `HTTPS_PROXY`/`HTTP_PROXY`/`NO_PROXY`
+    /// environment variables. See [`UreqClient::from_env`].
+    pub fn proxy_from_env(self) -> Result<Self> {
+        let client = UreqClient::from_env(self.endpoints.api_host())?;
+        Ok(self.http_client(client))
+    }
+
+    /// Overrides where requests are sent, e.g. to route them through a mock server.
+    pub fn endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Keeps up to `capacity` records fetched with `get` for `ttl`, answering repeat `get`s for
+    /// the same id without a request. This base's own updates and deletes evict the records
+    /// they touch, but changes made anywhere else go unnoticed until the entry expires, so pick
+    /// a `ttl` you can tolerate serving stale data for. Clones of the base share the cache.
+    pub fn cache_records(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.record_cache = Some(Arc::new(Mutex::new(LruCache::new(capacity, ttl))));
+        self
+    }
+
+    /// Caches the results of up to `capacity` distinct queries for `ttl`, keyed on everything
+    /// the query sends (view, formula, sort, fields and so on), so running the same query again
+    /// returns the same records without paging through the table. Only queries iterated to the
+    /// end are cached. Every write through this base empties the cache; changes made elsewhere
+    /// show up once an entry's `ttl` has passed.
+    pub fn cache_queries(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.query_cache = Some(Arc::new(Mutex::new(LruCache::new(capacity, ttl))));
+        self
+    }
+
+    /// A copy of this base pointing at the same table in another base, e.g. to run one query
+    /// across many bases a token can see without setting each one up. Everything else carries
+    /// over, the api key, transport and rate limiter included; caches start out empty rather
+    /// than being shared, as they only hold this base's records.
+    pub fn in_base(&self, app_key: &str) -> Self {
+        Base {
+            app_key: app_key.to_owned(),
+            schema_cache: self
+                .schema_cache
+                .as_ref()
+                .map(metadata::SchemaCache::emptied),
+            record_cache: self.record_cache.as_ref().map(emptied),
+            query_cache: self.query_cache.as_ref().map(emptied),
+            ..self.clone()
+        }
+    }
+
+    fn forget_cached(&self, id: &str) {
+        if let Some(ref cache) = self.record_cache {
+            cache.lock().unwrap().remove(id);
+        }
+    }
+
+    /// The link that opens the record in Airtable's web interface, as opposed to its API URL.
+    /// Airtable's own links use the table's id (`tbl...`) and often a view id as well; with a
+    /// base created from a table name the link may not resolve, so prefer creating the base
+    /// with the table id if you hand these out.
+    pub fn record_url(&self, id: &str) -> String {
+        format!(
+            "https://airtable.com/{}/{}/{}",
+            self.app_key,
+            endpoints::path_segment(&self.table),
+            id
+        )
+    }
+
+    // Pages through the records matching `params`, keeping only their ids.
+    fn record_ids(&self, params: &QueryParams) -> Result<Vec<String>> {
+        let url = self.records_endpoint();
+        let params = QueryParams {
+            fields: Some(vec![]),
+            ..params.clone()
         };
+        params.check()?;
+
+        let mut ids = vec![];
+        pagination::for_each_page(
+            None,
+            |offset: Option<&String>| {
+                let mut req = Request::new("GET", &url);
+                if let Some(offset) = offset {
+                    req = req.query("offset", offset);
+                }
+                let page: RecordPage<serde::de::IgnoredAny> =
+                    self.send(apply_query_params(req, &params))?.json()?;
+                Ok(page)
+            },
+            |page| {
+                ids.extend(page.into_iter().map(|record| record.id));
+                // Airtable stops at `max_records` itself, but don't rely on it.
+                match params.max_records {
+                    Some(max) if ids.len() >= max => {
+                        ids.truncate(max);
+                        Ok(ControlFlow::Break(()))
+                    }
+                    _ => Ok(ControlFlow::Continue(())),
+                }
+            },
+        )?;
+
+        Ok(ids)
+    }
+
+    fn distinct_values_in(&self, field: &str, params: &QueryParams) -> Result<Vec<String>> {
+        let url = self.records_endpoint();
+        let params = QueryParams {
+            fields: Some(vec![field.to_owned()]),
+            ..params.clone()
+        };
+        params.check()?;
+
+        let mut seen = HashSet::new();
+        let mut values = vec![];
+        let mut count = 0;
+        pagination::for_each_page(
+            None,
+            |offset: Option<&String>| {
+                let mut req = Request::new("GET", &url);
+                if let Some(offset) = offset {
+                    req = req.query("offset", offset);
+                }
+                let page: RecordPage<serde_json::Map<String, serde_json::Value>> =
+                    self.send(apply_query_params(req, &params))?.json()?;
+                Ok(page)
+            },
+            |page| {
+                for mut record in page {
+                    count += 1;
+                    if params.max_records.is_some_and(|max| count > max) {
+                        return Ok(ControlFlow::Break(()));
+                    }
+                    let value = record.fields.remove(field);
+                    let flattened = match value {
+                        Some(serde_json::Value::Array(values)) => values,
+                        Some(serde_json::Value::Null) | None => vec![],
+                        Some(value) => vec![value],
+                    };
+                    for value in flattened {
+                        let value = match value {
+                            serde_json::Value::String(value) => value,
+                            value => value.to_string(),
+                        };
+                        if seen.insert(value.clone()) {
+                            values.push(value);
+                        }
+                    }
+                }
+                Ok(ControlFlow::Continue(()))
+            },
+        )?;
+
+        Ok(values)
+    }
+
+    fn records_endpoint(&self) -> String {
+        self.endpoints.records(&self.app_key, &self.table)
+    }
 
-        let json = serde_json::to_string(&serializing_record)?;
+    fn record_endpoint(&self, id: &str) -> String {
+        self.endpoints.record(&self.app_key, &self.table, id)
+    }
+
+    /// Caps how large a page or record response may be before it's rejected with
+    /// `Error::ResponseTooLarge`, instead of buffering an unexpectedly huge body.
+    pub fn max_response_size(mut self, bytes: u64) -> Self {
+        self.max_response_size = bytes;
+        self
+    }
 
-        ureq::post(&url)
-            .set("Authorization", &format!("Bearer {}", &self.api_key))
-            .set("Content-Type", "application/json")
-            .send_string(&json)?;
+    /// Limits how long any request may take, reads and writes alike. Without a timeout the
+    /// HTTP client's own applies, which for the default ureq client is none at all.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.read_timeout(timeout).write_timeout(timeout)
+    }
+
+    /// Limits how long a read (a record or a page of them) may take, e.g. to allow large pages
+    /// longer than writes.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Limits how long a create, update or delete may take.
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Paces every request this base sends through `limiter`. Pass clones of one limiter to
+    /// several bases to keep them under a shared limit.
+    pub fn rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Paces this base to `requests_per_second`, never sending more than that in any one second.
+    /// Use `AIRTABLE_REQUESTS_PER_SECOND` for Airtable's own limit, or `rate_limiter` with a
+    /// shared limiter to keep several bases under one limit together.
+    pub fn with_rate_limit(self, requests_per_second: f64) -> Self {
+        self.rate_limiter(RateLimiter::new(requests_per_second))
+    }
+
+    /// Replaces how rate-limited requests are retried, by default `RetryPolicy::default()`.
+    /// Use `RetryPolicy::none()` to fail on the first 429 instead.
+    pub fn retry_policy(self, policy: RetryPolicy) -> Self {
+        self.retry_decider(policy)
+    }
+
+    /// Replaces the retry policy with custom rules for which failures to retry and how long
+    /// to wait first.
+    pub fn retry_decider(mut self, decider: impl RetryDecider + 'static) -> Self {
+        self.retry = Arc::new(decider);
+        self
+    }
+
+    /// Asks Airtable to convert written values to each field's type where it can, e.g. the
+    /// string `"2021-04-01"` into a date or `"42"` into a number, and to create select options
+    /// that don't exist yet. Applies to every create, update and upsert. Useful for imports
+    /// where every value starts out as text, together with
+    /// [`fields::serialize_number_from_str`].
+    pub fn typecast(mut self, typecast: bool) -> Self {
+        self.typecast = typecast;
+        self
+    }
+
+    /// Sorts the keys of every object in write bodies, so the same record always serializes to
+    /// the same bytes, even with fields in a `HashMap`. Handy for snapshot tests of outgoing
+    /// requests; it costs an extra pass over each body.
+    pub fn canonical_json(mut self) -> Self {
+        self.canonical_json = true;
+        self
+    }
+
+    /// Lets batch operations send up to `concurrency` chunk requests at once, overlapping their
+    /// network latency. Every request still waits for the rate limiter, so a higher value only
+    /// helps when latency rather than the rate limit is the bottleneck.
+    pub fn batch_concurrency(mut self, concurrency: usize) -> Self {
+        assert!(concurrency > 0, "batch concurrency must be at least 1");
+        self.batch_concurrency = concurrency;
+        self
+    }
+
+    /// How many records batch operations put in each request, from 1 to Airtable's limit of
+    /// `batch::BATCH_SIZE`, which is also the default. Smaller chunks mean smaller requests and
+    /// less to redo when one fails. Panics outside that range; see `batch_chunk_size_unchecked`
+    /// to go above it.
+    pub fn batch_chunk_size(self, size: usize) -> Self {
+        assert!(
+            (1..=batch::BATCH_SIZE).contains(&size),
+            "batch chunk size must be between 1 and {}",
+            batch::BATCH_SIZE
+        );
+        self.batch_chunk_size_unchecked(size)
+    }
+
+    /// Like `batch_chunk_size`, allowing sizes above `batch::BATCH_SIZE` in case Airtable
+    /// raises its limit before this crate does. Every batch request fails with a 422 if the
+    /// size is above what Airtable currently accepts.
+    pub fn batch_chunk_size_unchecked(mut self, size: usize) -> Self {
+        assert!(size > 0, "batch chunk size must be at least 1");
+        self.batch_chunk_size = size;
+        self
+    }
+
+    /// Caps the retries a batch operation makes across all of its chunks at `retries`, on top of
+    /// the retry policy's own limit for each request. Once they're spent, a chunk that fails
+    /// isn't retried and fails the operation, which bounds how long a large import can keep
+    /// going while Airtable is struggling. No cap by default.
+    pub fn batch_retry_budget(mut self, retries: usize) -> Self {
+        self.batch_retry_budget = Some(retries);
+        self
+    }
+
+    /// Calls `progress` after each chunk of a batch operation finishes, e.g. to drive a progress
+    /// bar through a large import.
+    pub fn on_batch_progress(
+        mut self,
+        progress: impl Fn(&batch::BatchProgress<'_>) + Send + Sync + 'static,
+    ) -> Self {
+        self.batch_progress = Some(batch::ProgressHook(Arc::new(progress)));
+        self
+    }
+
+    /// Calls `hook` with every request just before it's first sent, e.g. to add a tracing or
+    /// correlation header, or to sign the request for a gateway. Retries resend the headers as
+    /// they were. An error from `hook` fails the request without sending it.
+    pub fn on_request(
+        mut self,
+        hook: impl Fn(&mut http::OutgoingRequest<'_>) -> Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.request_hook = Some(http::RequestHook(Arc::new(hook)));
+        self
+    }
+
+    /// Adds a header to every request, e.g. for an API gateway in front of Airtable. Setting
+    /// `Content-Type` replaces the default `application/json`. `Authorization` is reserved for
+    /// the api key and rejected with `Error::ReservedHeader`.
+    pub fn header(mut self, name: &str, value: &str) -> Result<Self> {
+        if name.eq_ignore_ascii_case("Authorization") {
+            return Err(Error::ReservedHeader {
+                name: name.to_owned(),
+            }
+            .into());
+        }
+        self.headers
+            .retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_owned(), value.to_owned()));
+        Ok(self)
+    }
+
+    /// The current state of the base's rate limiter, if it has one.
+    pub fn rate_limit_state(&self) -> Option<RateLimitState> {
+        self.rate_limiter.as_ref().map(RateLimiter::state)
+    }
+
+    // Warns, once per process, when Airtable signals the api key is a deprecated kind. Legacy
+    // api keys (`key...`) have been replaced by personal access tokens (`pat...`); responses to
+    // deprecated credentials carry a `Deprecation` or `Sunset` header, and once they're
+    // switched off they're rejected outright.
+    fn warn_if_deprecated_key(&self, response: &Response) {
+        static WARNED: AtomicBool = AtomicBool::new(false);
+
+        let deprecated = response.header("Deprecation").is_some()
+            || response.header("Sunset").is_some()
+            || (response.status == 401 && self.api_key.starts_with("key"));
+        if deprecated && !WARNED.swap(true, Ordering::Relaxed) {
+            warn!(
+                "Airtable reports the api key for base {} is deprecated; switch to a personal access token (https://airtable.com/create/tokens)",
+                self.app_key
+            );
+        }
+    }
+
+    // Tells a misconfigured base or table apart from a missing record, which both come back as
+    // a 404.
+    fn not_found_error(&self, url: &str, response: &Response) -> Option<Error> {
+        match error_type(response).as_deref() {
+            Some("TABLE_NOT_FOUND") | Some("MODEL_ID_NOT_FOUND") => Some(Error::TableNotFound {
+                table: self.table.clone(),
+            }),
+            _ if url == self.records_endpoint() => Some(Error::TableNotFound {
+                table: self.table.clone(),
+            }),
+            _ => {
+                let prefix = format!("{}/", self.records_endpoint());
+                url.strip_prefix(&prefix)
+                    .map(|id| Error::RecordNotFound { id: id.to_owned() })
+            }
+        }
+    }
+
+    /// Checks the api key is valid and the table reachable with the cheapest request there is,
+    /// a single record with no fields, e.g. for a readiness probe. Fails with
+    /// `Error::Unauthorized` for a bad key, `Error::TableNotFound` for a wrong app key or table,
+    /// and with the transport's error when Airtable can't be reached at all.
+    pub async fn ping(&self) -> Result<()> {
+        let url = self.records_endpoint();
+        let req = Request::new("GET", &url)
+            .query("maxRecords", "1")
+            .query("fields[]", "");
+        self.send(req)?;
+        Ok(())
+    }
+
+    /// Creates a record from a JSON object of field names to values, for tools that don't know
+    /// the table's columns at compile time. Returns the created record as Airtable sent it,
+    /// with its `id`, `createdTime` and `fields`. Retried like `create`.
+    pub async fn create_raw(&self, fields: &serde_json::Value) -> Result<serde_json::Value> {
+        if !fields.is_object() {
+            return Err(Error::FieldsNotAnObject { id: String::new() }.into());
+        }
+
+        let body = self.write_body(&serde_json::json!({ "fields": fields }))?;
+        let url = self.records_endpoint();
+        self.send(Request::new("POST", &url).body(body))?.json()
+    }
+
+    // Every write body goes through here so `typecast` applies to all of them.
+    fn write_body<B: Serialize>(&self, body: &B) -> Result<String> {
+        #[derive(Serialize)]
+        struct WriteBody<'a, B> {
+            #[serde(flatten)]
+            body: &'a B,
+            #[serde(skip_serializing_if = "std::ops::Not::not")]
+            typecast: bool,
+        }
+
+        let body = WriteBody {
+            body,
+            typecast: self.typecast,
+        };
+        if self.canonical_json {
+            return Ok(serde_json::to_string(&sort_keys(serde_json::to_value(
+                &body,
+            )?))?);
+        }
+        Ok(serde_json::to_string(&body)?)
+    }
+
+    fn send(&self, request: Request) -> Result<Response> {
+        Ok(self.send_timed(request)?.value)
+    }
+
+    // Any write may change what a cached query would return, so all of them are dropped.
+    fn invalidate_queries(&self, request: &Request) {
+        if request.method != "GET" {
+            if let Some(ref cache) = self.query_cache {
+                cache.lock().unwrap().clear();
+            }
+        }
+    }
+
+    // Every request goes through here so auth and status handling stay in one place.
+    fn send_timed(&self, request: Request) -> Result<Timed<Response>> {
+        self.send_within_budget(request, None)
+    }
+
+    // Sends the request like `send_timed`, with each retry taking one from `retry_budget`, if
+    // given. No retries are made once it reaches zero.
+    fn send_within_budget(
+        &self,
+        mut request: Request,
+        retry_budget: Option<&AtomicUsize>,
+    ) -> Result<Timed<Response>> {
+        let start = Instant::now();
+        let url = request.url.clone();
+        if !has_header(&self.headers, "Content-Type") {
+            request = request.header("Content-Type", "application/json");
+        }
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        if let Some(ref hook) = self.request_hook {
+            (hook.0)(&mut http::OutgoingRequest::new(&mut request))?;
+        }
+        // Added last so no hook ever sees the api key.
+        request = request.header("Authorization", &format!("Bearer {}", &self.api_key));
+        request.max_response_size = Some(self.max_response_size);
+        request.timeout = if request.method == "GET" {
+            self.read_timeout
+        } else {
+            self.write_timeout
+        };
+
+        // Before sending, since a failed write may still have changed the table, and again once
+        // it's done in case a query cached the old records in the meantime.
+        self.invalidate_queries(&request);
+        let mut attempts = 0;
+        let mut throttled = Duration::default();
+        let response = loop {
+            attempts += 1;
+            if let Some(ref limiter) = self.rate_limiter {
+                let waiting = Instant::now();
+                limiter.acquire();
+                throttled += waiting.elapsed();
+            }
+
+            let result = self.http.send(request.clone());
+            if let (Some(limiter), Ok(response)) = (&self.rate_limiter, &result) {
+                limiter.observe(response);
+            }
+            if matches!(result, Ok(ref response) if response.is_success()) {
+                break result?;
+            }
+
+            let attempt = retry::Attempt {
+                number: attempts,
+                request: &request,
+                response: result.as_ref().ok(),
+                error: result.as_ref().err(),
+                elapsed: start.elapsed(),
+            };
+            let wait = match self.retry.should_retry(&attempt) {
+                Some(wait) => wait,
+                None => break result?,
+            };
+            if let Some(budget) = retry_budget {
+                if budget
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                        left.checked_sub(1)
+                    })
+                    .is_err()
+                {
+                    warn!(
+                        "Batch retry budget spent, not retrying request to table {}",
+                        self.table
+                    );
+                    break result?;
+                }
+            }
+            // Only the table and Airtable's request id: the request itself carries the api key.
+            match result {
+                Ok(response) => warn!(
+                    "Request to table {} failed with status {} (attempt {}), retrying in {:?}; request id {}",
+                    self.table,
+                    response.status,
+                    attempts,
+                    wait,
+                    response.header("X-Request-Id").unwrap_or("unknown")
+                ),
+                Err(err) => warn!(
+                    "Request to table {} failed (attempt {}), retrying in {:?}: {}",
+                    self.table, attempts, wait, err
+                ),
+            }
+            std::thread::sleep(wait);
+        };
+        self.invalidate_queries(&request);
+        self.warn_if_deprecated_key(&response);
+        // A custom client may not enforce the limit while reading, so check here too.
+        if response.body.len() as u64 > self.max_response_size {
+            return Err(Error::ResponseTooLarge {
+                limit: self.max_response_size,
+            }
+            .into());
+        }
+        if response.status == 401 {
+            return Err(Error::Unauthorized.into());
+        }
+        if response.status == 404 {
+            if let Some(err) = self.not_found_error(&url, &response) {
+                return Err(err.into());
+            }
+        }
+        if response.status == 422 {
+            if let Some(err) = missing_fields_error(&response) {
+                return Err(err.into());
+            }
+        }
+        if !response.is_success() {
+            return Err(anyhow!("{}: status code {}", url, response.status));
+        }
+
+        Ok(Timed {
+            value: response,
+            elapsed: start.elapsed(),
+            throttled,
+            attempts,
+        })
+    }
+}
+
+impl<T> Base<T>
+where
+    for<'de> T: Deserialize<'de>,
+    T: Record,
+{
+    /// The ids of every record in the table. See `QueryBuilder::record_ids` to narrow them down
+    /// with a view or formula.
+    pub async fn list_record_ids(&self) -> Result<Vec<String>> {
+        self.record_ids(&QueryParams::default())
+    }
+
+    /// Counts the table's records, stopping at `n`. See `QueryBuilder::count_up_to`.
+    pub async fn count_up_to(&self, n: usize) -> Result<usize> {
+        self.query().count_up_to(n)
+    }
+
+    /// Reads every record in each of `views` and returns each record once, however many of the
+    /// views it's in, e.g. to work through an "Active" and a "Flagged" view without handling a
+    /// record twice. Records come in the order they're first seen, going through the views in
+    /// the order given.
+    ///
+    /// The views are read concurrently, like `fetch_tables`, sharing this base's rate limiter.
+    /// Fails if any view does.
+    pub async fn fetch_views(&self, views: &[&str]) -> Result<Vec<T>>
+    where
+        T: Send + Sync,
+    {
+        let mut unique_views: Vec<&str> = Vec::with_capacity(views.len());
+        for view in views {
+            if !unique_views.contains(view) {
+                unique_views.push(view);
+            }
+        }
+
+        let queries = unique_views
+            .iter()
+            .map(|view| (*view, self.query().view(*view)))
+            .collect();
+        let mut results = fetch_tables(queries);
+
+        let mut seen = HashSet::new();
+        let mut records = Vec::new();
+        for view in unique_views {
+            let view_records = results
+                .remove(view)
+                .unwrap_or_else(|| Ok(Vec::new()))
+                .with_context(|| format!("reading view {:?}", view))?;
+            for record in view_records {
+                if seen.insert(record.id().clone()) {
+                    records.push(record);
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    /// Sends a request to an endpoint the crate doesn't wrap yet, with the same api key,
+    /// headers, retries and rate limiting as every other request. `path` is joined to the API
+    /// root, e.g. `/meta/bases/app123/tables`, and may carry a query string. `method` is one of
+    /// `GET`, `POST`, `PUT`, `PATCH` or `DELETE`, in any case.
+    ///
+    /// The response comes back as raw JSON, `null` when the body is empty, and the body is
+    /// sent as it is: `typecast` and `canonical_json` only apply to the wrapped endpoints.
+    pub async fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        let method = method.to_ascii_uppercase();
+        if !["GET", "POST", "PUT", "PATCH", "DELETE"].contains(&method.as_str()) {
+            return Err(Error::InvalidMethod { method }.into());
+        }
+        let url = format!(
+            "{}/{}",
+            self.endpoints.api.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        );
+
+        let mut request = Request::new(&method, &url);
+        if let Some(body) = body {
+            request = request.body(serde_json::to_string(body)?);
+        }
+        let response = self.send(request)?;
+        if response.body.trim().is_empty() {
+            return Ok(serde_json::Value::Null);
+        }
+        response.json()
+    }
+
+    pub fn query(&self) -> QueryBuilder<'_, T> {
+        QueryBuilder {
+            base: self,
+            params: QueryParams::default(),
+            cancel: None,
+            skip_invalid: false,
+            empty_fields_as_null: false,
+            adaptive_page_size: false,
+            unknown_fields: UnknownFields::Ignore,
+            validator: None,
+        }
+    }
+
+    /// Every value of `field` in the table, without duplicates. Use
+    /// `QueryBuilder::distinct_values` to only look at a view or the records matching a formula.
+    pub async fn distinct_values(&self, field: &str) -> Result<Vec<String>> {
+        self.query().distinct_values(field)
+    }
+
+    /// Shorthand for `query().formula(formula)`, the most common query there is.
+    pub fn where_formula(&self, formula: impl Into<String>) -> QueryBuilder<'_, T> {
+        self.query().formula(formula)
+    }
+
+    pub async fn get(&self, id: &str) -> Result<T> {
+        Ok(self.get_timed(id).await?.value)
+    }
+
+    /// Like `get`, also reporting the request's duration and attempts as a [`Timed`].
+    pub async fn get_timed(&self, id: &str) -> Result<Timed<T>> {
+        if let Some(ref cache) = self.record_cache {
+            let cached = cache.lock().unwrap().get(id);
+            if let Some(body) = cached {
+                let record: SRecord<T> = serde_json::from_str(&body)?;
+                return Ok(Timed {
+                    value: record.into_record(),
+                    elapsed: Duration::default(),
+                    throttled: Duration::default(),
+                    attempts: 0,
+                });
+            }
+        }
+
+        let url = self.record_endpoint(id);
+        let response = self.send_timed(Request::new("GET", &url))?;
+        let record: SRecord<T> = response.value.json()?;
+
+        if let Some(ref cache) = self.record_cache {
+            cache
+                .lock()
+                .unwrap()
+                .insert(id, response.value.body.clone());
+        }
+        Ok(response.with_value(record.into_record()))
+    }
+
+    /// Like `get`, with cell values formatted as in `QueryBuilder::format`. Always fetches the
+    /// record, as the record cache only holds the default format.
+    pub async fn get_formatted(&self, id: &str, format: &FormatOptions) -> Result<T> {
+        format.check()?;
+        let url = self.record_endpoint(id);
+        let record: SRecord<T> = self.send(format.apply(Request::new("GET", &url)))?.json()?;
+        Ok(record.into_record())
+    }
+
+    /// Like `get`, but only transfers the listed fields. The fields left out must deserialize
+    /// to something, e.g. through `#[serde(default)]`.
+    ///
+    /// The single-record endpoint doesn't take a `fields[]` selection, so this goes through the
+    /// list endpoint filtered on `RECORD_ID()`.
+    pub async fn get_fields(&self, id: &str, fields: &[&str]) -> Result<T> {
+        let url = self.records_endpoint();
+        let mut req = Request::new("GET", &url)
+            .query(
+                "filterByFormula",
+                &format!("RECORD_ID() = {}", formula::quote(id)),
+            )
+            .query("maxRecords", "1");
+
+        for field in fields {
+            req = req.query("fields[]", field);
+        }
+
+        let page: RecordPage<T> = self.send(req)?.json()?;
+        page.records
+            .into_iter()
+            .next()
+            .map(SRecord::into_record)
+            .ok_or_else(|| Error::RecordNotFound { id: id.to_owned() }.into())
+    }
+
+    /// Fetches the records with the given ids, e.g. to resolve a linked record field, in table
+    /// order rather than the order of `ids`. Ids that don't exist are left out.
+    ///
+    /// The ids are matched with an `OR(RECORD_ID() = ...)` formula, split into several queries
+    /// of at most 50 ids each to stay under Airtable's URL length limit.
+    pub async fn get_many<S: AsRef<str>>(&self, ids: &[S]) -> Result<Vec<T>> {
+        let mut records = Vec::with_capacity(ids.len());
+
+        for chunk in ids.chunks(GET_MANY_CHUNK_SIZE) {
+            let matches: Vec<String> = chunk
+                .iter()
+                .map(|id| format!("RECORD_ID() = {}", formula::quote(id.as_ref())))
+                .collect();
+            let formula = format!("OR({})", matches.join(", "));
+            records.extend(self.query().formula(formula).collect_all()?);
+        }
+
+        Ok(records)
+    }
+
+    /// Like `get_many`, but keyed by id, so ids missing from the map are the ones that don't
+    /// exist, e.g. dangling links.
+    pub async fn get_many_by_id<S: AsRef<str>>(&self, ids: &[S]) -> Result<HashMap<String, T>> {
+        let records = self.get_many(ids).await?;
+        Ok(records
+            .into_iter()
+            .map(|record| (record.id().to_string(), record))
+            .collect())
+    }
+
+    /// Like `get_many`, but lined up with `ids`: each position holds the record with that id,
+    /// or `None` if there's no such record.
+    pub async fn get_many_aligned<S: AsRef<str>>(&self, ids: &[S]) -> Result<Vec<Option<T>>>
+    where
+        T: Clone,
+    {
+        let records = self.get_many_by_id(ids).await?;
+        Ok(ids
+            .iter()
+            .map(|id| records.get(id.as_ref()).cloned())
+            .collect())
+    }
+
+    /// Creates the record. A create that fails without a response, e.g. on a timeout, may
+    /// still have gone through, so unlike reads, updates and deletes it isn't retried unless
+    /// `RetryPolicy::retry_creates` is set; see `create_idempotent` for a create that's always
+    /// safe to retry. Rate-limited creates are retried either way.
+    pub async fn create(&self, record: &T) -> Result<()>
+    where
+        T: serde::Serialize,
+    {
+        self.create_timed(record).await?;
         Ok(())
     }
 
+    /// Like `create`, also reporting the request's duration and attempts as a [`Timed`].
+    pub async fn create_timed(&self, record: &T) -> Result<Timed<()>>
+    where
+        T: serde::Serialize,
+    {
+        Ok(self.post_record(record)?.map(|_| ()))
+    }
+
+    /// Creates the record and returns just its new id, without deserializing the rest of the
+    /// response. Retried like `create`.
+    pub async fn create_id(&self, record: &T) -> Result<String>
+    where
+        T: serde::Serialize,
+    {
+        #[derive(Deserialize)]
+        struct Created {
+            id: String,
+        }
+
+        let created: Created = self.post_record(record)?.value.json()?;
+        Ok(created.id)
+    }
+
+    /// Creates a record from a different type than the one it's read back as, e.g. a struct
+    /// without an `id` or without the fields Airtable computes. Returns the created record.
+    /// Retried like `create`.
+    pub async fn create_as<B: Serialize>(&self, body: &B) -> Result<T> {
+        let url = self.records_endpoint();
+        let json = self.write_body(&SRecord {
+            id: String::new(),
+            fields: body,
+            created_time: None,
+            comment_count: None,
+        })?;
+
+        let created: SRecord<T> = self.send(Request::new("POST", &url).body(json))?.json()?;
+        Ok(created.into_record())
+    }
+
+    fn post_record(&self, record: &T) -> Result<Timed<Response>>
+    where
+        T: serde::Serialize,
+    {
+        let url = self.records_endpoint();
+
+        let serializing_record = SRecord::from(record);
+
+        let json = self.write_body(&serializing_record)?;
+
+        self.send_timed(Request::new("POST", &url).body(json))
+    }
+
     // TODO: Perhaps pass a mutable reference to allow updating computed fields when someone does
     // an update?
     //
     // TODO: Include the error body in the error.
+    /// Updates the record with a PATCH, which only touches the fields `record` serializes. A
+    /// struct modelling some of a table's columns leaves the others as they are in Airtable.
+    /// Fields serialized as `null` are cleared though, so give `Option` fields that shouldn't
+    /// be written `skip_serializing_if = "Option::is_none"`.
     pub async fn update(&self, record: &T) -> Result<()>
     where
         T: serde::Serialize,
     {
-        let url = format!("{}/{}/{}/{}", URL, self.app_key, self.table, record.id());
+        self.update_by_id(record.id(), record).await?;
+        Ok(())
+    }
+
+    /// Updates the record with the given id, ignoring whatever `record.id()` returns. Handy when
+    /// the id comes from somewhere other than the struct. Returns the record as Airtable stored it.
+    pub async fn update_by_id(&self, id: &str, record: &T) -> Result<T>
+    where
+        T: serde::Serialize,
+    {
+        Ok(self.update_by_id_timed(id, record).await?.value)
+    }
 
-        let serializing_record = SRecord {
-            id: record.id().to_owned(),
-            fields: record,
+    /// Like `update`, also reporting the request's duration and attempts as a [`Timed`].
+    pub async fn update_timed(&self, record: &T) -> Result<Timed<T>>
+    where
+        T: serde::Serialize,
+    {
+        self.update_by_id_timed(record.id(), record).await
+    }
+
+    /// Fetches the record as currently stored and only updates it if `predicate` returns true
+    /// for it, failing with `Error::PreconditionFailed` otherwise. Airtable has no ETags to
+    /// make this atomic: a change landing between the fetch and the update still goes unseen.
+    pub async fn update_if(&self, record: &T, predicate: impl Fn(&T) -> bool) -> Result<T>
+    where
+        T: serde::Serialize,
+    {
+        let id = record.id();
+        require_id(id)?;
+        // The cached copy may be exactly the stale state the predicate is there to catch.
+        self.forget_cached(id);
+        let current = self.get(id).await?;
+        if !predicate(&current) {
+            return Err(Error::PreconditionFailed { id: id.to_string() }.into());
+        }
+        self.update_by_id(id, record).await
+    }
+
+    /// Adds `values` to the multi-value field `field`, such as a linked record or multiple
+    /// select field, leaving the values already in it alone, and returns the updated record.
+    /// Values the field already holds aren't added twice.
+    ///
+    /// Airtable has no way to append to a field, so this reads the record, merges and sends the
+    /// whole list back. Another write to the field landing between the read and the update is
+    /// lost; `append_to_field_retrying` at least catches writes that overwrite this one.
+    pub async fn append_to_field<V: Serialize>(
+        &self,
+        id: &str,
+        field: &str,
+        values: &[V],
+    ) -> Result<T> {
+        self.append_to_field_retrying(id, field, values, 0).await
+    }
+
+    /// Like `append_to_field`, but reads the record back afterwards and, if another write has
+    /// dropped any of `values` from the field, tries again up to `retries` times before failing
+    /// with `Error::ConcurrentWrite`.
+    pub async fn append_to_field_retrying<V: Serialize>(
+        &self,
+        id: &str,
+        field: &str,
+        values: &[V],
+        retries: usize,
+    ) -> Result<T> {
+        require_id(id)?;
+        let values = values
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<serde_json::Result<Vec<_>>>()?;
+        let url = self.record_endpoint(id);
+        let field_values = |record: &serde_json::Value| match &record["fields"][field] {
+            serde_json::Value::Array(values) => values.clone(),
+            _ => Vec::new(),
         };
 
-        let json = serde_json::to_string(&serializing_record)?;
+        // Reads skip the record cache, which may hold the very state being merged into.
+        let record: serde_json::Value = self.send(Request::new("GET", &url))?.json()?;
+        let mut current = field_values(&record);
+        if values.iter().all(|value| current.contains(value)) {
+            let record: SRecord<T> = serde_json::from_value(record)?;
+            return Ok(record.into_record());
+        }
 
-        ureq::request("PATCH", &url)
-            .set("Authorization", &format!("Bearer {}", &self.api_key))
-            .set("Content-Type", "application/json")
-            .send_string(&json)?;
+        for attempt in 0..=retries {
+            for value in &values {
+                if !current.contains(value) {
+                    current.push(value.clone());
+                }
+            }
+            let body = self.write_body(&serde_json::json!({ "fields": { field: current } }))?;
+            let updated: SRecord<T> = self.send(Request::new("PATCH", &url).body(body))?.json()?;
+            self.forget_cached(id);
+            if retries == 0 {
+                return Ok(updated.into_record());
+            }
 
-        Ok(())
+            let record: serde_json::Value = self.send(Request::new("GET", &url))?.json()?;
+            current = field_values(&record);
+            if values.iter().all(|value| current.contains(value)) {
+                return Ok(updated.into_record());
+            }
+            warn!(
+                "Values appended to {} on record {} were overwritten (attempt {}), retrying",
+                field,
+                id,
+                attempt + 1
+            );
+        }
+        Err(Error::ConcurrentWrite { id: id.to_owned() }.into())
+    }
+
+    async fn update_by_id_timed(&self, id: &str, record: &T) -> Result<Timed<T>>
+    where
+        T: serde::Serialize,
+    {
+        require_id(id)?;
+        let url = self.record_endpoint(id);
+
+        let serializing_record = SRecord {
+            id: id.to_owned(),
+            ..SRecord::from(record)
+        };
+
+        let json = self.write_body(&serializing_record)?;
+
+        let response = self.send_timed(Request::new("PATCH", &url).body(json))?;
+        self.forget_cached(id);
+        let updated: SRecord<T> = response.value.json()?;
+        Ok(response.with_value(updated.into_record()))
     }
 }
@@ -0,0 +1,563 @@
+//! The metadata API, describing the bases and tables the token can see.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::http::Request;
+use crate::pagination::{self, CursorPage};
+use crate::{Base, Error, Record};
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BaseInfo {
+    pub id: String,
+    pub name: String,
+    pub permission_level: PermissionLevel,
+}
+
+/// What the api key may do in a base. Levels are ordered by what they allow, each including
+/// the ones before it, so checking for write access is `level >= PermissionLevel::Edit`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionLevel {
+    None,
+    Read,
+    Comment,
+    Edit,
+    Create,
+}
+
+impl PermissionLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PermissionLevel::None => "none",
+            PermissionLevel::Read => "read",
+            PermissionLevel::Comment => "comment",
+            PermissionLevel::Edit => "edit",
+            PermissionLevel::Create => "create",
+        }
+    }
+}
+
+impl fmt::Display for PermissionLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PermissionLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(level: &str) -> Result<Self> {
+        match level {
+            "none" => Ok(PermissionLevel::None),
+            "read" => Ok(PermissionLevel::Read),
+            "comment" => Ok(PermissionLevel::Comment),
+            "edit" => Ok(PermissionLevel::Edit),
+            "create" => Ok(PermissionLevel::Create),
+            _ => Err(anyhow!("unknown permission level {:?}", level)),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TableSchema {
+    pub id: String,
+    pub name: String,
+    pub primary_field_id: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub fields: Vec<FieldSchema>,
+    #[serde(default)]
+    pub views: Vec<ViewSchema>,
+}
+
+impl TableSchema {
+    pub fn field(&self, name_or_id: &str) -> Option<&FieldSchema> {
+        self.fields
+            .iter()
+            .find(|field| field.name == name_or_id || field.id == name_or_id)
+    }
+
+    /// A hash of the schema that only changes when the schema does, for telling cheaply whether
+    /// something built from an earlier copy (a cache, a validation setup) is stale. The order
+    /// of fields and views and of keys in the field options doesn't count, as Airtable makes no
+    /// promises about it. The hash is stable across runs and builds, so it can be stored.
+    pub fn hash(&self) -> u64 {
+        let mut fields: Vec<_> = self
+            .fields
+            .iter()
+            .map(|field| {
+                serde_json::json!([
+                    field.id,
+                    field.name,
+                    field.field_type,
+                    field.description,
+                    field.options
+                ])
+            })
+            .collect();
+        fields.sort_by(|a, b| a[0].as_str().cmp(&b[0].as_str()));
+        let mut views: Vec<_> = self
+            .views
+            .iter()
+            .map(|view| serde_json::json!([view.id, view.name, view.view_type]))
+            .collect();
+        views.sort_by(|a, b| a[0].as_str().cmp(&b[0].as_str()));
+
+        let normalized = crate::sort_keys(serde_json::json!([
+            self.id,
+            self.name,
+            self.primary_field_id,
+            self.description,
+            fields,
+            views
+        ]));
+        // FNV-1a, rather than `DefaultHasher`, whose output may change between Rust releases.
+        normalized
+            .to_string()
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+
+    /// Maps each field's id to its name, e.g. `fldWord` to `Word`.
+    pub fn field_names_by_id(&self) -> HashMap<String, String> {
+        self.fields
+            .iter()
+            .map(|field| (field.id.clone(), field.name.clone()))
+            .collect()
+    }
+
+    /// Maps each field's name to its id, the reverse of `field_names_by_id`.
+    pub fn field_ids_by_name(&self) -> HashMap<String, String> {
+        self.fields
+            .iter()
+            .map(|field| (field.name.clone(), field.id.clone()))
+            .collect()
+    }
+}
+
+/// A problem `TableSchema::validate_record` found with fields about to be written.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    /// The fields weren't a JSON object.
+    NotAnObject,
+    /// The table has no field with this name.
+    UnknownField { field: String },
+    /// The field is computed by Airtable and can't be written.
+    ReadOnlyField { field: String },
+    /// The value doesn't have the JSON type the field takes, e.g. a string for a number.
+    WrongType {
+        field: String,
+        expected: &'static str,
+    },
+    /// The value isn't one of the select field's choices.
+    InvalidOption { field: String, option: String },
+    /// One of the fields passed to `validate_record_requiring` is missing or empty.
+    MissingField { field: String },
+}
+
+impl TableSchema {
+    /// Checks fields about to be written, keyed by field name, against the schema, returning
+    /// every problem found rather than stopping at the first. Catches what Airtable would
+    /// reject without spending a request on it. With `typecast` Airtable converts some values
+    /// and adds missing select options itself, so some of these issues may not apply.
+    pub fn validate_record(&self, fields: &serde_json::Value) -> Vec<ValidationIssue> {
+        let fields = match fields.as_object() {
+            Some(fields) => fields,
+            None => return vec![ValidationIssue::NotAnObject],
+        };
+
+        let mut issues = vec![];
+        for (name, value) in fields {
+            match self.fields.iter().find(|field| &field.name == name) {
+                Some(field) => field.check_value(value, &mut issues),
+                None => issues.push(ValidationIssue::UnknownField {
+                    field: name.clone(),
+                }),
+            }
+        }
+        issues
+    }
+
+    /// Like `validate_record`, also requiring a non-empty value for each of `required`.
+    /// Airtable itself has no required fields, so which ones are is up to the caller.
+    pub fn validate_record_requiring(
+        &self,
+        fields: &serde_json::Value,
+        required: &[&str],
+    ) -> Vec<ValidationIssue> {
+        let mut issues = self.validate_record(fields);
+        for &name in required {
+            let missing = match fields.get(name) {
+                None | Some(serde_json::Value::Null) => true,
+                Some(serde_json::Value::String(value)) => value.is_empty(),
+                Some(serde_json::Value::Array(values)) => values.is_empty(),
+                Some(_) => false,
+            };
+            if missing && fields.is_object() {
+                issues.push(ValidationIssue::MissingField {
+                    field: name.to_owned(),
+                });
+            }
+        }
+        issues
+    }
+}
+
+/// Renames the field ids in a response read with `QueryBuilder::return_fields_by_field_id` back
+/// to the field names in `schema`, for code that works on raw JSON without serde renames but
+/// wants reads that survive a column being renamed. `value` can be a record's `fields` object,
+/// a record (anything with `id` and `fields`), or a page of them (anything with `records`).
+/// Keys that aren't field ids in `schema`, e.g. for a field added since it was fetched, are
+/// left as they are.
+pub fn remap_field_ids(value: serde_json::Value, schema: &TableSchema) -> serde_json::Value {
+    use serde_json::Value;
+
+    let mut object = match value {
+        Value::Object(object) => object,
+        value => return value,
+    };
+    if let Some(Value::Array(records)) = object.remove("records") {
+        let records = records
+            .into_iter()
+            .map(|record| remap_field_ids(record, schema))
+            .collect();
+        object.insert("records".to_owned(), Value::Array(records));
+        return Value::Object(object);
+    }
+    if object.contains_key("id") && object.get("fields").is_some_and(Value::is_object) {
+        if let Some(fields) = object.remove("fields") {
+            object.insert("fields".to_owned(), remap_field_ids(fields, schema));
+        }
+        return Value::Object(object);
+    }
+
+    object
+        .into_iter()
+        .map(|(key, value)| {
+            let name = match schema.fields.iter().find(|field| field.id == key) {
+                Some(field) => field.name.clone(),
+                None => key,
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldSchema {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Type-specific configuration, e.g. the choices of a select field.
+    #[serde(default)]
+    pub options: Option<serde_json::Value>,
+}
+
+impl FieldSchema {
+    /// The field's type along with its options, for the types whose options say something
+    /// about the values. Every other type comes back as `FieldKind::Other`, as does a field
+    /// whose options don't have the expected shape.
+    pub fn kind(&self) -> FieldKind {
+        FieldKind::from_parts(&self.field_type, self.options.as_ref())
+    }
+
+    fn check_value(&self, value: &serde_json::Value, issues: &mut Vec<ValidationIssue>) {
+        use serde_json::Value;
+
+        // Null clears a field of any type.
+        if value.is_null() {
+            return;
+        }
+        let wrong_type = |expected| ValidationIssue::WrongType {
+            field: self.name.clone(),
+            expected,
+        };
+        let check_option =
+            |option: &Value, choices: &[Choice], issues: &mut Vec<_>| match option.as_str() {
+                Some(option) if choices.iter().any(|choice| choice.name == option) => {}
+                Some(option) => issues.push(ValidationIssue::InvalidOption {
+                    field: self.name.clone(),
+                    option: option.to_owned(),
+                }),
+                None => issues.push(wrong_type("string")),
+            };
+
+        match self.kind() {
+            FieldKind::SingleSelect { choices } => check_option(value, &choices, issues),
+            FieldKind::MultipleSelects { choices } => match value.as_array() {
+                Some(options) => {
+                    for option in options {
+                        check_option(option, &choices, issues);
+                    }
+                }
+                None => issues.push(wrong_type("array")),
+            },
+            FieldKind::Formula { .. } | FieldKind::Rollup { .. } | FieldKind::Lookup { .. } => {
+                issues.push(ValidationIssue::ReadOnlyField {
+                    field: self.name.clone(),
+                })
+            }
+            FieldKind::Link(_) => {
+                if !matches!(value, Value::Array(ids) if ids.iter().all(Value::is_string)) {
+                    issues.push(wrong_type("array of record ids"));
+                }
+            }
+            FieldKind::Other { .. } => match self.field_type.as_str() {
+                "count" | "autoNumber" | "createdTime" | "lastModifiedTime" | "createdBy"
+                | "lastModifiedBy" | "button" => issues.push(ValidationIssue::ReadOnlyField {
+                    field: self.name.clone(),
+                }),
+                field_type => {
+                    let expected = match field_type {
+                        "singleLineText" | "multilineText" | "richText" | "email" | "url"
+                        | "phoneNumber" | "date" | "dateTime" => "string",
+                        "number" | "currency" | "percent" | "duration" | "rating" => "number",
+                        "checkbox" => "boolean",
+                        "multipleAttachments" => "array",
+                        _ => return,
+                    };
+                    let matches = match expected {
+                        "string" => value.is_string(),
+                        "number" => value.is_number(),
+                        "boolean" => value.is_boolean(),
+                        _ => value.is_array(),
+                    };
+                    if !matches {
+                        issues.push(wrong_type(expected));
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// A field type together with its typed options, see [`FieldSchema::kind`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldKind {
+    SingleSelect {
+        choices: Vec<Choice>,
+    },
+    MultipleSelects {
+        choices: Vec<Choice>,
+    },
+    /// A link to records in another table.
+    Link(LinkOptions),
+    /// A computed field. `result` is the type of the values it produces, when Airtable reports
+    /// one, e.g. a formula returning a number.
+    Formula {
+        result: Option<Box<FieldKind>>,
+    },
+    Rollup {
+        result: Option<Box<FieldKind>>,
+    },
+    Lookup {
+        result: Option<Box<FieldKind>>,
+    },
+    Other {
+        field_type: String,
+    },
+}
+
+/// One of the options of a select field.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Choice {
+    pub id: String,
+    pub name: String,
+    /// Absent for choices on fields that don't color their options.
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkOptions {
+    pub linked_table_id: String,
+    /// The field in the linked table that links back, if there is one.
+    #[serde(default)]
+    pub inverse_link_field_id: Option<String>,
+    #[serde(default)]
+    pub prefers_single_record_link: bool,
+}
+
+#[derive(Deserialize)]
+struct SelectOptions {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct ComputedOptions {
+    #[serde(default)]
+    result: Option<ComputedResult>,
+}
+
+#[derive(Deserialize)]
+struct ComputedResult {
+    #[serde(rename = "type")]
+    field_type: String,
+    #[serde(default)]
+    options: Option<serde_json::Value>,
+}
+
+impl FieldKind {
+    fn from_parts(field_type: &str, options: Option<&serde_json::Value>) -> FieldKind {
+        fn parse<O: for<'de> Deserialize<'de>>(options: Option<&serde_json::Value>) -> Option<O> {
+            serde_json::from_value(options?.clone()).ok()
+        }
+        let computed = |options| {
+            parse::<ComputedOptions>(options).map(|options| {
+                options.result.map(|result| {
+                    Box::new(FieldKind::from_parts(
+                        &result.field_type,
+                        result.options.as_ref(),
+                    ))
+                })
+            })
+        };
+
+        let kind = match field_type {
+            "singleSelect" => parse(options)
+                .map(|o: SelectOptions| FieldKind::SingleSelect { choices: o.choices }),
+            "multipleSelects" => parse(options)
+                .map(|o: SelectOptions| FieldKind::MultipleSelects { choices: o.choices }),
+            "multipleRecordLinks" => parse(options).map(FieldKind::Link),
+            "formula" => computed(options).map(|result| FieldKind::Formula { result }),
+            "rollup" => computed(options).map(|result| FieldKind::Rollup { result }),
+            "multipleLookupValues" => computed(options).map(|result| FieldKind::Lookup { result }),
+            _ => None,
+        };
+
+        kind.unwrap_or_else(|| FieldKind::Other {
+            field_type: field_type.to_owned(),
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ViewSchema {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub view_type: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Tables {
+    tables: Vec<TableSchema>,
+}
+
+// Shared between clones of the cache so every user of a base sees the same entry.
+#[derive(Debug, Clone)]
+pub(crate) struct SchemaCache {
+    ttl: Duration,
+    entry: Arc<Mutex<Option<(Instant, TableSchema)>>>,
+}
+
+impl SchemaCache {
+    pub(crate) fn emptied(&self) -> Self {
+        SchemaCache {
+            ttl: self.ttl,
+            entry: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct BasesPage {
+    bases: Vec<BaseInfo>,
+    #[serde(default)]
+    offset: Option<String>,
+}
+
+impl CursorPage for BasesPage {
+    type Item = BaseInfo;
+    type Cursor = String;
+
+    fn into_parts(self) -> (Vec<BaseInfo>, Option<String>) {
+        (self.bases, self.offset)
+    }
+}
+
+impl<T: Record> Base<T> {
+    /// Lists every base the api key has access to, across all pages.
+    pub async fn list_bases(&self) -> Result<Vec<BaseInfo>> {
+        let url = self.endpoints.bases();
+
+        pagination::fetch_all(None, |offset: Option<&String>| {
+            let mut req = Request::new("GET", &url);
+            if let Some(offset) = offset {
+                req = req.query("offset", offset);
+            }
+            let page: BasesPage = self.send(req)?.json()?;
+            Ok(page)
+        })
+    }
+}
+
+impl<T: Record> Base<T> {
+    /// Keeps the result of `schema` for `ttl` rather than fetching it on every call.
+    pub fn cache_schema(mut self, ttl: Duration) -> Self {
+        self.schema_cache = Some(SchemaCache {
+            ttl,
+            entry: Arc::new(Mutex::new(None)),
+        });
+        self
+    }
+
+    /// Lists the schema of every table in this base.
+    pub async fn tables(&self) -> Result<Vec<TableSchema>> {
+        let url = self.endpoints.tables(&self.app_key);
+        let tables: Tables = self.send(Request::new("GET", &url))?.json()?;
+        Ok(tables.tables)
+    }
+
+    /// The schema of this base's table, looked up by name or id. Fails with
+    /// `Error::TableNotFound` if the base has no such table.
+    pub async fn schema(&self) -> Result<TableSchema> {
+        if let Some(ref cache) = self.schema_cache {
+            if let Some((fetched, ref schema)) = *cache.entry.lock().unwrap() {
+                if fetched.elapsed() < cache.ttl {
+                    return Ok(schema.clone());
+                }
+            }
+        }
+
+        let schema = self
+            .tables()
+            .await?
+            .into_iter()
+            .find(|table| table.name == self.table || table.id == self.table)
+            .ok_or_else(|| Error::TableNotFound {
+                table: self.table.clone(),
+            })?;
+
+        if let Some(ref cache) = self.schema_cache {
+            *cache.entry.lock().unwrap() = Some((Instant::now(), schema.clone()));
+        }
+        Ok(schema)
+    }
+
+    /// A hash of the table's schema, from `schema`. See `TableSchema::hash`.
+    pub async fn schema_hash(&self) -> Result<u64> {
+        Ok(self.schema().await?.hash())
+    }
+
+    /// Maps each of the table's field ids to its field name, from `schema`. Use `cache_schema`
+    /// to avoid fetching the schema each call, and `TableSchema::field_ids_by_name` for the
+    /// other direction.
+    pub async fn field_map(&self) -> Result<HashMap<String, String>> {
+        Ok(self.schema().await?.field_names_by_id())
+    }
+}
@@ -0,0 +1,116 @@
+//! Moving records in and out of a table as newline-delimited JSON, one record per line.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::ops::ControlFlow;
+
+use crate::http::Request;
+use crate::pagination::{self, CursorPage};
+use crate::{Base, Error, Record};
+
+// The response to an import chunk, read only to count the records Airtable created.
+#[derive(Deserialize, Debug)]
+struct CreatedRecords {
+    records: Vec<serde::de::IgnoredAny>,
+}
+
+// A page of records kept exactly as Airtable sent them, so an export loses nothing the record
+// type doesn't know about.
+#[derive(Deserialize, Debug)]
+struct RawPage {
+    records: Vec<serde_json::Value>,
+    #[serde(default)]
+    offset: Option<String>,
+}
+
+impl CursorPage for RawPage {
+    type Item = serde_json::Value;
+    type Cursor = String;
+
+    fn into_parts(self) -> (Vec<serde_json::Value>, Option<String>) {
+        (
+            self.records,
+            self.offset.filter(|offset| !offset.is_empty()),
+        )
+    }
+}
+
+impl<T: Record> Base<T> {
+    /// Writes every record in the table to `writer` as one JSON object per line, with its
+    /// `id`, `createdTime` and `fields` as Airtable returned them, and returns how many were
+    /// written. Pages are written as they arrive, so memory use doesn't grow with the table.
+    /// An error part way through leaves the lines written so far in `writer`.
+    pub async fn export_ndjson<W: Write>(&self, mut writer: W) -> Result<usize> {
+        let url = self.records_endpoint();
+        let mut written = 0;
+
+        pagination::for_each_page(
+            None,
+            |offset: Option<&String>| {
+                let mut req = Request::new("GET", &url);
+                if let Some(offset) = offset {
+                    req = req.query("offset", offset);
+                }
+                let page: RawPage = self.send(req)?.json()?;
+                Ok(page)
+            },
+            |records| {
+                for record in records {
+                    serde_json::to_writer(&mut writer, &record)?;
+                    writer.write_all(b"\n")?;
+                    written += 1;
+                }
+                Ok(ControlFlow::Continue(()))
+            },
+        )?;
+
+        writer.flush()?;
+        Ok(written)
+    }
+
+    /// Creates a record for each line of `reader`, where every line is a JSON object of field
+    /// names to values, ten records per request, and returns how many were created. Blank lines
+    /// are skipped. With `typecast` set on the base, Airtable converts values such as `"42"` to
+    /// the field's type.
+    ///
+    /// Every line is read and checked before anything is sent, so a malformed line fails the
+    /// import, naming its line number, without creating any records. A request failing part way
+    /// through leaves the records from earlier requests in place.
+    pub async fn import_records<R: Read>(&self, reader: R) -> Result<usize> {
+        let mut records = vec![];
+        for (index, line) in BufReader::new(reader).lines().enumerate() {
+            let number = index + 1;
+            let line = line.with_context(|| format!("failed to read line {}", number))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: serde_json::Value = serde_json::from_str(&line)
+                .with_context(|| format!("line {} is not valid JSON", number))?;
+            if !fields.is_object() {
+                return Err(
+                    anyhow::Error::from(Error::FieldsNotAnObject { id: String::new() })
+                        .context(format!("line {} is not a record", number)),
+                );
+            }
+            records.push(json!({ "fields": fields }));
+        }
+
+        let url = self.records_endpoint();
+        let requests = records
+            .chunks(self.batch_chunk_size)
+            .map(|chunk| {
+                let body = self.write_body(&json!({ "records": chunk }))?;
+                Ok(Request::new("POST", &url).body(body))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut created = 0;
+        for response in self.send_all(requests, records.len())? {
+            let response: CreatedRecords = response.json()?;
+            created += response.records.len();
+        }
+        Ok(created)
+    }
+}
@@ -0,0 +1,54 @@
+//! Cursor pagination shared by every endpoint that pages its results.
+
+use anyhow::Result;
+use std::ops::ControlFlow;
+
+/// A single page of results along with the cursor for the next one, if any.
+pub(crate) trait CursorPage {
+    type Item;
+    type Cursor;
+
+    fn into_parts(self) -> (Vec<Self::Item>, Option<Self::Cursor>);
+}
+
+/// Fetches every page, feeding each page's cursor into the next fetch, until the endpoint stops
+/// returning one.
+pub(crate) fn fetch_all<P, F>(start: Option<P::Cursor>, fetch: F) -> Result<Vec<P::Item>>
+where
+    P: CursorPage,
+    F: FnMut(Option<&P::Cursor>) -> Result<P>,
+{
+    let mut items = vec![];
+    for_each_page(start, fetch, |page| {
+        items.extend(page);
+        Ok(ControlFlow::Continue(()))
+    })?;
+    Ok(items)
+}
+
+/// Like `fetch_all`, but hands each page to `each` as it arrives rather than collecting them.
+/// Stops once `each` breaks or at the first error from either.
+pub(crate) fn for_each_page<P, F, G>(
+    start: Option<P::Cursor>,
+    mut fetch: F,
+    mut each: G,
+) -> Result<()>
+where
+    P: CursorPage,
+    F: FnMut(Option<&P::Cursor>) -> Result<P>,
+    G: FnMut(Vec<P::Item>) -> Result<ControlFlow<()>>,
+{
+    let mut cursor = start;
+
+    loop {
+        let (page, next) = fetch(cursor.as_ref())?.into_parts();
+        if each(page)?.is_break() {
+            return Ok(());
+        }
+
+        match next {
+            Some(next) => cursor = Some(next),
+            None => return Ok(()),
+        }
+    }
+}
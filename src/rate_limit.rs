@@ -0,0 +1,150 @@
+//! Client-side rate limiting. Airtable allows 5 requests per second per base and answers
+//! anything faster with a 429 and a 30 second penalty, so it's cheaper to pace ourselves.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::http::Response;
+
+/// Airtable's documented per-base request rate.
+pub const AIRTABLE_REQUESTS_PER_SECOND: f64 = 5.0;
+
+/// A token bucket that can be shared between bases (clones share the same bucket), e.g. to keep
+/// several tables of one base under the per-base limit together.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+    recent: VecDeque<Instant>,
+    server_remaining: Option<u64>,
+    server_reset: Option<String>,
+}
+
+/// A snapshot of a [`RateLimiter`], for monitoring.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitState {
+    /// Requests that could be sent right now without waiting.
+    pub tokens_available: f64,
+    pub requests_last_second: usize,
+    /// The last `X-RateLimit-Remaining` Airtable sent, if it has sent one.
+    pub server_remaining: Option<u64>,
+    /// The last `X-RateLimit-Reset` Airtable sent, if it has sent one.
+    pub server_reset: Option<String>,
+}
+
+impl Bucket {
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+
+        while let Some(&sent) = self.recent.front() {
+            if now.duration_since(sent) < Duration::from_secs(1) {
+                break;
+            }
+            self.recent.pop_front();
+        }
+    }
+}
+
+impl RateLimiter {
+    /// Allows `requests_per_second` requests per second. Up to that many may go out at once,
+    /// but never more than that within any one second, so a burst at the start can't double
+    /// up with the requests that follow it.
+    pub fn new(requests_per_second: f64) -> Self {
+        assert!(
+            requests_per_second > 0.0,
+            "rate limit must be a positive number of requests per second"
+        );
+        let capacity = requests_per_second.max(1.0);
+
+        RateLimiter {
+            bucket: Arc::new(Mutex::new(Bucket {
+                rate: requests_per_second,
+                capacity,
+                tokens: capacity,
+                last_refill: Instant::now(),
+                recent: VecDeque::new(),
+                server_remaining: None,
+                server_reset: None,
+            })),
+        }
+    }
+
+    /// Blocks until a request may be sent.
+    pub fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                bucket.refill();
+                // The bucket alone would let a full burst through and then refill as it went,
+                // sending up to twice the rate in the first second.
+                let window_full = bucket.recent.len() >= bucket.capacity.floor() as usize;
+                if bucket.tokens >= 1.0 && !window_full {
+                    bucket.tokens -= 1.0;
+                    bucket.recent.push_back(Instant::now());
+                    return;
+                }
+                let refilled =
+                    Duration::from_secs_f64((1.0 - bucket.tokens).max(0.0) / bucket.rate);
+                let window_moves = match bucket.recent.front() {
+                    Some(&oldest) if window_full => {
+                        Duration::from_secs(1).saturating_sub(oldest.elapsed())
+                    }
+                    _ => Duration::ZERO,
+                };
+                refilled.max(window_moves).max(Duration::from_millis(1))
+            };
+            thread::sleep(wait);
+        }
+    }
+
+    /// Reports the limiter's current state. Only holds the lock for as long as it takes to copy
+    /// the numbers out, so it's cheap to poll.
+    pub fn state(&self) -> RateLimitState {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.refill();
+        let window_left = (bucket.capacity.floor() as usize).saturating_sub(bucket.recent.len());
+        RateLimitState {
+            tokens_available: bucket.tokens.min(window_left as f64),
+            requests_last_second: bucket.recent.len(),
+            server_remaining: bucket.server_remaining,
+            server_reset: bucket.server_reset.clone(),
+        }
+    }
+
+    // Remembers whatever rate-limit headers Airtable sent with a response.
+    pub(crate) fn observe(&self, response: &Response) {
+        let remaining = response
+            .header("X-RateLimit-Remaining")
+            .and_then(|value| value.trim().parse().ok());
+        let reset = response.header("X-RateLimit-Reset");
+        if remaining.is_none() && reset.is_none() {
+            return;
+        }
+
+        let mut bucket = self.bucket.lock().unwrap();
+        if remaining.is_some() {
+            bucket.server_remaining = remaining;
+        }
+        if let Some(reset) = reset {
+            bucket.server_reset = Some(reset.to_owned());
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        RateLimiter::new(AIRTABLE_REQUESTS_PER_SECOND)
+    }
+}
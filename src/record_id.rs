@@ -0,0 +1,105 @@
+//! The id Airtable gives each record, kept apart from plain strings so it can't be mixed up with
+//! field values or other kinds of ids.
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use crate::Error;
+
+/// A record id, e.g. `rec8116cdd76088af`. It dereferences to `str`, so it can be passed
+/// wherever the crate takes an id as `&str`, e.g. `base.get(&word.id)`.
+///
+/// `From<String>` and `From<&str>` take any string as is, for ids that came from Airtable or a
+/// trusted store. Parsing with `str::parse` checks the id looks like a record id, rejecting
+/// e.g. an app key with `Error::InvalidRecordId`.
+///
+/// Migrating from `String` ids: change the struct's `id: String` field to `id: RecordId` (the
+/// serde attributes stay the same) and, for hand-written `Record` impls, make `id` return
+/// `&RecordId` and `set_id` take a `RecordId`. An empty `RecordId::default()` still marks a
+/// record that hasn't been created yet.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[serde(transparent)]
+pub struct RecordId(String);
+
+impl RecordId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl Deref for RecordId {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for RecordId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for RecordId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RecordId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for RecordId {
+    type Err = Error;
+
+    /// Accepts `rec` followed by letters and digits.
+    fn from_str(id: &str) -> Result<Self, Error> {
+        match id.strip_prefix("rec") {
+            Some(rest) if !rest.is_empty() && rest.bytes().all(|b| b.is_ascii_alphanumeric()) => {
+                Ok(RecordId(id.to_owned()))
+            }
+            _ => Err(Error::InvalidRecordId { id: id.to_owned() }),
+        }
+    }
+}
+
+impl From<String> for RecordId {
+    fn from(id: String) -> Self {
+        RecordId(id)
+    }
+}
+
+impl From<&str> for RecordId {
+    fn from(id: &str) -> Self {
+        RecordId(id.to_owned())
+    }
+}
+
+impl From<RecordId> for String {
+    fn from(id: RecordId) -> Self {
+        id.0
+    }
+}
+
+impl PartialEq<str> for RecordId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for RecordId {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
@@ -0,0 +1,117 @@
+//! Retrying requests Airtable turned away. A 429 means the request wasn't processed at all, so
+//! it's always safe to send again once the penalty has passed. A request that failed without a
+//! response may or may not have been processed, which only matters for creates: sending a read,
+//! update or delete twice does no harm, but a create sent twice makes two records.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::http::{Request, Response};
+use crate::Error;
+
+/// A failed attempt at a request, for a [`RetryDecider`] to judge.
+#[derive(Debug)]
+pub struct Attempt<'a> {
+    /// Counting from 1.
+    pub number: u32,
+    pub request: &'a Request,
+    /// The error response, or `None` if the request failed without one.
+    pub response: Option<&'a Response>,
+    /// Why the request failed without a response.
+    pub error: Option<&'a anyhow::Error>,
+    /// Since the first attempt was sent.
+    pub elapsed: Duration,
+}
+
+/// Decides whether and when a failed request is sent again, for rules a [`RetryPolicy`] can't
+/// express, e.g. honoring a header a proxy adds. See `Base::retry_decider`.
+pub trait RetryDecider: Send + Sync + fmt::Debug {
+    /// Returns how long to wait before the next attempt, or `None` to give up and fail with
+    /// this attempt's error. Successful responses never get here.
+    fn should_retry(&self, attempt: &Attempt<'_>) -> Option<Duration>;
+}
+
+/// How a base retries rate-limited requests and ones that failed without a response, other than
+/// with one of the crate's own [`Error`]s. Each retry waits twice as long as the one before,
+/// starting at `initial_backoff` and capped at `max_backoff`, unless Airtable says how long to
+/// wait with a `Retry-After` header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first one. 1 disables retries.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Gives up once retrying would take the request past this long in total, returning the
+    /// last error, however many attempts are left. `None` allows any duration.
+    pub max_retry_duration: Option<Duration>,
+    /// Also retries creates (`POST`s) that failed without a response. Off by default, since
+    /// the first attempt may have created the record already.
+    pub retry_creates: bool,
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts, waiting 1 and then 2 seconds.
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            max_retry_duration: None,
+            retry_creates: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Sends every request once.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    // `attempt` is the attempt that just failed, counting from 1.
+    fn backoff(&self, attempt: u32, response: Option<&Response>) -> Duration {
+        if let Some(seconds) = response
+            .and_then(|response| response.header("Retry-After"))
+            .and_then(|value| value.trim().parse::<u64>().ok())
+        {
+            return Duration::from_secs(seconds).min(self.max_backoff);
+        }
+
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+
+    // Whether waiting `wait` more after `elapsed` stays within `max_retry_duration`.
+    fn allows_wait(&self, elapsed: Duration, wait: Duration) -> bool {
+        self.max_retry_duration
+            .is_none_or(|budget| elapsed + wait <= budget)
+    }
+}
+
+impl RetryDecider for RetryPolicy {
+    fn should_retry(&self, attempt: &Attempt<'_>) -> Option<Duration> {
+        if attempt.number >= self.max_attempts {
+            return None;
+        }
+        let retryable = match attempt.response {
+            Some(response) => response.status == 429,
+            None => {
+                // The crate's own errors, e.g. a body over the size limit, would fail the same
+                // way again.
+                let own_error = attempt.error.is_some_and(|err| err.is::<Error>());
+                !own_error && (attempt.request.method != "POST" || self.retry_creates)
+            }
+        };
+        if !retryable {
+            return None;
+        }
+
+        let wait = self.backoff(attempt.number, attempt.response);
+        Some(wait).filter(|&wait| self.allows_wait(attempt.elapsed, wait))
+    }
+}
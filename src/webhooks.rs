@@ -0,0 +1,133 @@
+//! Webhook payloads, the change feed Airtable keeps for each webhook on a base.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::http::Request;
+use crate::pagination::{self, CursorPage};
+use crate::{Base, Record};
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload {
+    pub timestamp: String,
+    pub base_transaction_number: u64,
+    pub payload_format: String,
+    #[serde(default)]
+    pub action_metadata: serde_json::Value,
+    #[serde(default)]
+    pub changed_tables_by_id: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PayloadsPage {
+    payloads: Vec<WebhookPayload>,
+    cursor: u64,
+    might_have_more: bool,
+}
+
+impl CursorPage for PayloadsPage {
+    type Item = WebhookPayload;
+    type Cursor = u64;
+
+    // Airtable always returns a cursor; `mightHaveMore` is what says whether to follow it.
+    fn into_parts(self) -> (Vec<WebhookPayload>, Option<u64>) {
+        let next = if self.might_have_more {
+            Some(self.cursor)
+        } else {
+            None
+        };
+        (self.payloads, next)
+    }
+}
+
+impl<T: Record> Base<T> {
+    /// Lists the payloads for a webhook on this base, starting at `cursor` (or the oldest
+    /// retained payload) and following the cursor until Airtable reports no more.
+    pub async fn webhook_payloads(
+        &self,
+        webhook_id: &str,
+        cursor: Option<u64>,
+    ) -> Result<Vec<WebhookPayload>> {
+        Ok(self.webhook_payloads_from(webhook_id, cursor)?.0)
+    }
+
+    // Returns the payloads along with the cursor to resume from afterwards.
+    fn webhook_payloads_from(
+        &self,
+        webhook_id: &str,
+        cursor: Option<u64>,
+    ) -> Result<(Vec<WebhookPayload>, Option<u64>)> {
+        let url = self.endpoints.webhook_payloads(&self.app_key, webhook_id);
+        let mut next_cursor = cursor;
+
+        let payloads = pagination::fetch_all(cursor, |cursor: Option<&u64>| {
+            let mut req = Request::new("GET", &url);
+            if let Some(cursor) = cursor {
+                req = req.query("cursor", &cursor.to_string());
+            }
+            let page: PayloadsPage = self.send(req)?.json()?;
+            next_cursor = Some(page.cursor);
+            Ok(page)
+        })?;
+
+        Ok((payloads, next_cursor))
+    }
+}
+
+/// Where a [`WebhookPoller`] is up to. Persist it to pick up after a restart without reprocessing
+/// payloads.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PollerState {
+    pub webhook_id: String,
+    pub cursor: Option<u64>,
+}
+
+/// Turns a webhook's payloads endpoint into a change feed. `poll` returns the payloads since
+/// the last acknowledged cursor; `ack` moves past them once they've been processed. Polling
+/// again without acknowledging returns the same payloads.
+pub struct WebhookPoller<'base, T: Record> {
+    base: &'base Base<T>,
+    state: PollerState,
+    pending: Option<u64>,
+}
+
+impl<'base, T: Record> WebhookPoller<'base, T> {
+    pub fn new(base: &'base Base<T>, webhook_id: &str) -> Self {
+        WebhookPoller::from_state(
+            base,
+            PollerState {
+                webhook_id: webhook_id.to_owned(),
+                cursor: None,
+            },
+        )
+    }
+
+    pub fn from_state(base: &'base Base<T>, state: PollerState) -> Self {
+        WebhookPoller {
+            base,
+            state,
+            pending: None,
+        }
+    }
+
+    pub fn state(&self) -> &PollerState {
+        &self.state
+    }
+
+    pub async fn poll(&mut self) -> Result<Vec<WebhookPayload>> {
+        let (payloads, next) = self
+            .base
+            .webhook_payloads_from(&self.state.webhook_id, self.state.cursor)?;
+        self.pending = next;
+        Ok(payloads)
+    }
+
+    /// Marks the payloads returned by the last `poll` as processed.
+    pub fn ack(&mut self) {
+        if let Some(cursor) = self.pending.take() {
+            self.state.cursor = Some(cursor);
+        }
+    }
+}
@@ -0,0 +1,353 @@
+mod common;
+
+use common::{base, block_on, FakeClient, Word};
+use serde_json::json;
+
+#[test]
+fn upsert_splits_created_from_updated() {
+    let client = FakeClient::new();
+    client.respond(200, include_str!("fixtures/upsert.json"));
+
+    let words = vec![
+        Word {
+            word: "lurid".to_owned(),
+            google: 6870000,
+            ..Default::default()
+        },
+        Word {
+            word: "limpid".to_owned(),
+            google: 900000,
+            ..Default::default()
+        },
+    ];
+    let result = block_on(base(&client).upsert(&words, &["Word"])).unwrap();
+
+    let created: Vec<&str> = result.created.iter().map(|w| w.id.as_str()).collect();
+    let updated: Vec<&str> = result.updated.iter().map(|w| w.id.as_str()).collect();
+    assert_eq!(created, ["rec9"]);
+    assert_eq!(updated, ["rec1"]);
+
+    let request = &client.requests()[0];
+    assert_eq!(request.method, "PATCH");
+    let body: serde_json::Value = serde_json::from_str(request.body.as_ref().unwrap()).unwrap();
+    assert_eq!(
+        body["performUpsert"],
+        json!({ "fieldsToMergeOn": ["Word"] })
+    );
+    assert_eq!(body["records"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn concurrent_batches_stay_under_the_rate_limit() {
+    let client = FakeClient::new();
+    let page = include_str!("fixtures/page_2.json");
+    for _ in 0..6 {
+        client.respond(200, page);
+    }
+
+    let limiter = airtable::RateLimiter::new(4.0);
+    let base = base(&client)
+        .rate_limiter(limiter.clone())
+        .batch_concurrency(3);
+    let words = vec![Word::default(); 60];
+
+    let start = std::time::Instant::now();
+    let created = block_on(base.create_batch(&words)).unwrap();
+
+    // A burst of 4, then no more until a second has passed, however many are in flight.
+    assert!(start.elapsed() >= std::time::Duration::from_millis(950));
+    assert!(limiter.state().requests_last_second <= 4);
+    assert_eq!(client.requests().len(), 6);
+    assert_eq!(created.len(), 6);
+}
+
+#[test]
+fn batch_progress_is_reported_per_chunk() {
+    let client = FakeClient::new();
+    let page = include_str!("fixtures/page_2.json");
+    client
+        .respond(200, page)
+        .respond(200, page)
+        .respond(422, "{}");
+
+    let reports = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let seen = reports.clone();
+    let base = base(&client)
+        .batch_concurrency(1)
+        .on_batch_progress(move |progress| {
+            seen.lock()
+                .unwrap()
+                .push((progress.done, progress.total, progress.error.is_some()));
+        });
+
+    let words = vec![Word::default(); 25];
+    assert!(block_on(base.create_batch(&words)).is_err());
+    assert_eq!(
+        *reports.lock().unwrap(),
+        [(10, 25, false), (20, 25, false), (20, 25, true)]
+    );
+}
+
+#[test]
+fn failed_batches_report_the_chunks_sent_alongside_the_failure() {
+    let client = FakeClient::new();
+    let chunk = |ids: std::ops::Range<usize>| {
+        let records: Vec<_> = ids
+            .map(|i| json!({ "id": format!("rec{}", i), "fields": { "Word": "new" } }))
+            .collect();
+        json!({ "records": records }).to_string()
+    };
+    // The three chunks of the first group are in flight at once, so any of them may get the 422.
+    client
+        .respond(200, &chunk(0..10))
+        .respond(422, "{}")
+        .respond(200, &chunk(10..20));
+
+    let words = vec![Word::default(); 35];
+    let err = block_on(base(&client).batch_concurrency(3).create_batch(&words)).unwrap_err();
+
+    let incomplete = err
+        .downcast_ref::<airtable::batch::IncompleteBatch>()
+        .unwrap();
+    let mut written = incomplete.written.clone();
+    written.sort_by_key(|id| id[3..].parse::<usize>().unwrap());
+    let expected: Vec<String> = (0..20).map(|i| format!("rec{}", i)).collect();
+    assert_eq!(written, expected);
+    assert_eq!(client.requests().len(), 3);
+}
+
+#[test]
+fn cancelled_batches_stop_after_the_chunk_in_flight() {
+    let client = FakeClient::new();
+    let chunk = |ids: std::ops::Range<usize>| {
+        let records: Vec<_> = ids
+            .map(|i| json!({ "id": format!("rec{}", i), "fields": { "Word": "new" } }))
+            .collect();
+        json!({ "records": records }).to_string()
+    };
+    client
+        .respond(200, &chunk(0..10))
+        .respond(200, &chunk(10..20))
+        .respond(200, &chunk(20..25));
+
+    let cancel = airtable::CancellationToken::new();
+    let on_progress = cancel.clone();
+    let base = base(&client)
+        .batch_concurrency(1)
+        .on_batch_progress(move |_| on_progress.cancel());
+
+    let words = vec![Word::default(); 25];
+    let outcome = block_on(base.create_batch_cancellable(&words, &cancel)).unwrap();
+
+    assert!(outcome.cancelled);
+    assert_eq!(outcome.records.len(), 10);
+    assert_eq!(outcome.records[9].id, "rec9");
+    assert_eq!(client.requests().len(), 1);
+
+    let outcome =
+        block_on(base.create_batch_cancellable(&words[10..], &airtable::CancellationToken::new()))
+            .unwrap();
+    assert!(!outcome.cancelled);
+    assert_eq!(outcome.records.len(), 15);
+}
+
+#[test]
+fn create_batch_ids_keeps_input_order_across_chunks() {
+    let client = FakeClient::new();
+    let chunk = |ids: std::ops::Range<usize>| {
+        let records: Vec<_> = ids
+            .map(|i| json!({ "id": format!("rec{}", i), "fields": { "Word": "new" } }))
+            .collect();
+        json!({ "records": records }).to_string()
+    };
+    client
+        .respond(200, &chunk(0..10))
+        .respond(200, &chunk(10..12));
+
+    let words = vec![Word::default(); 12];
+    let ids = block_on(base(&client).batch_concurrency(1).create_batch_ids(&words)).unwrap();
+
+    let expected: Vec<String> = (0..12).map(|i| format!("rec{}", i)).collect();
+    assert_eq!(ids, expected);
+    assert_eq!(client.requests().len(), 2);
+}
+
+#[test]
+fn resumable_upserts_carry_on_from_the_last_checkpoint() {
+    let client = FakeClient::new();
+    let created = |ids: std::ops::Range<usize>| {
+        let records: Vec<_> = ids
+            .clone()
+            .map(|i| json!({ "id": format!("rec{}", i), "fields": { "Word": format!("word{}", i) } }))
+            .collect();
+        let created: Vec<_> = ids.map(|i| format!("rec{}", i)).collect();
+        json!({ "records": records, "createdRecords": created }).to_string()
+    };
+    client
+        .respond(200, &created(0..10))
+        .respond(200, &created(10..20))
+        .respond(422, "{}");
+
+    let words: Vec<Word> = (0..25)
+        .map(|i| Word {
+            word: format!("word{}", i),
+            ..Default::default()
+        })
+        .collect();
+    let base = base(&client).batch_concurrency(1);
+    let mut saved = None;
+    let failed = block_on(base.upsert_resumable(&words, &["Word"], None, |token| {
+        saved = Some(token.to_string())
+    }));
+    assert!(failed.is_err());
+    assert_eq!(saved.as_deref(), Some("20/25"));
+
+    client.respond(200, &created(20..25));
+    let token = saved.unwrap().parse().unwrap();
+    let resumed = block_on(base.upsert_resumable(&words, &["Word"], Some(token), |_| {})).unwrap();
+
+    assert_eq!(resumed.created.len(), 5);
+    let requests = client.requests();
+    assert_eq!(requests.len(), 4);
+    let body: serde_json::Value = serde_json::from_str(requests[3].body.as_ref().unwrap()).unwrap();
+    assert_eq!(body["records"].as_array().unwrap().len(), 5);
+    assert_eq!(body["records"][0]["fields"]["Word"], "word20");
+}
+
+#[test]
+fn batch_chunk_size_sets_records_per_request() {
+    for (size, requests) in [(3, 4), (7, 2), (10, 1)] {
+        let client = FakeClient::new();
+        let ids: Vec<String> = (0..10).map(|i| format!("rec{}", i)).collect();
+        for chunk in ids.chunks(size) {
+            let records: Vec<_> = chunk
+                .iter()
+                .map(|id| json!({ "id": id, "deleted": true }))
+                .collect();
+            client.respond(200, &json!({ "records": records }).to_string());
+        }
+
+        let base = base(&client).batch_chunk_size(size).batch_concurrency(1);
+        let deleted = block_on(base.delete_batch(&ids)).unwrap();
+
+        assert_eq!(deleted, ids);
+        let sent = client.requests();
+        assert_eq!(sent.len(), requests);
+        assert_eq!(common::query(&sent[0], "records[]").len(), size);
+    }
+}
+
+#[test]
+#[should_panic(expected = "batch chunk size must be between 1 and 10")]
+fn batch_chunk_size_rejects_sizes_above_the_limit() {
+    base(&FakeClient::new()).batch_chunk_size(11);
+}
+
+#[test]
+fn diff_keeps_only_changed_fields() {
+    #[derive(serde::Serialize, Default, Clone)]
+    struct Entry {
+        #[serde(skip_serializing)]
+        id: airtable::RecordId,
+        #[serde(rename = "Word")]
+        word: String,
+        #[serde(rename = "Google")]
+        google: i64,
+        #[serde(rename = "Note", skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+    }
+    airtable::impl_record!(Entry);
+
+    let old = Entry {
+        id: "rec1".into(),
+        word: "lurid".to_owned(),
+        google: 6870000,
+        note: Some("vivid".to_owned()),
+    };
+    assert_eq!(airtable::diff(&old, &old.clone()).unwrap(), json!({}));
+
+    let new = Entry {
+        id: "rec2".into(),
+        google: 6900000,
+        note: None,
+        ..old.clone()
+    };
+    let changed = airtable::diff(&old, &new).unwrap();
+    assert_eq!(changed, json!({ "Google": 6900000, "Note": null }));
+
+    let client = FakeClient::new();
+    client.respond(200, r#"{ "records": [] }"#);
+    block_on(base(&client).update_batch_partial(&[("rec1".to_owned(), changed)])).unwrap();
+    let body: serde_json::Value =
+        serde_json::from_str(client.requests()[0].body.as_ref().unwrap()).unwrap();
+    assert_eq!(
+        body,
+        json!({ "records": [{ "id": "rec1", "fields": { "Google": 6900000, "Note": null } }] })
+    );
+}
+
+#[test]
+fn batch_retry_budget_caps_retries_across_chunks() {
+    let client = FakeClient::new();
+    let deleted = |ids: std::ops::Range<usize>| {
+        let records: Vec<_> = ids
+            .map(|i| json!({ "id": format!("rec{}", i), "deleted": true }))
+            .collect();
+        json!({ "records": records }).to_string()
+    };
+    client
+        .respond(429, "{}")
+        .respond(200, &deleted(0..10))
+        .respond(429, "{}")
+        .respond(200, &deleted(10..20))
+        .respond(429, "{}")
+        .respond(200, &deleted(20..30));
+
+    let base = base(&client)
+        .retry_policy(airtable::RetryPolicy {
+            initial_backoff: std::time::Duration::from_millis(1),
+            ..Default::default()
+        })
+        .batch_concurrency(1)
+        .batch_retry_budget(2);
+    let ids: Vec<String> = (0..30).map(|i| format!("rec{}", i)).collect();
+
+    assert!(block_on(base.delete_batch(&ids)).is_err());
+    assert_eq!(client.requests().len(), 5);
+
+    // Each operation gets a budget of its own.
+    let client = FakeClient::new();
+    client.respond(429, "{}").respond(200, &deleted(0..10));
+    let base = base.http_client(client.clone());
+    assert_eq!(block_on(base.delete_batch(&ids[..10])).unwrap().len(), 10);
+    assert_eq!(client.requests().len(), 2);
+}
+
+#[test]
+fn create_all_sends_an_iterator_in_chunks() {
+    let client = FakeClient::new();
+    let chunk = |ids: std::ops::Range<usize>| {
+        let records: Vec<_> = ids
+            .map(|i| json!({ "id": format!("rec{}", i), "fields": { "Word": format!("w{}", i) } }))
+            .collect();
+        json!({ "records": records }).to_string()
+    };
+    client
+        .respond(200, &chunk(0..10))
+        .respond(200, &chunk(10..20))
+        .respond(200, &chunk(20..25));
+
+    let words = (0..25).map(|i| Word {
+        word: format!("w{}", i),
+        ..Word::default()
+    });
+    let created = block_on(base(&client).create_all(words)).unwrap();
+
+    assert_eq!(created.len(), 25);
+    assert_eq!(created[24].id, "rec24");
+    let requests = client.requests();
+    assert_eq!(requests.len(), 3);
+    let last: serde_json::Value = serde_json::from_str(requests[2].body.as_ref().unwrap()).unwrap();
+    assert_eq!(last["records"].as_array().unwrap().len(), 5);
+    assert_eq!(last["records"][0]["fields"]["Word"], "w20");
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use airtable::cassette::CassetteClient;
+use common::{block_on, FakeClient, Word};
+use std::path::PathBuf;
+
+const PAGE_1: &str = include_str!("fixtures/page_1.json");
+const PAGE_2: &str = include_str!("fixtures/page_2.json");
+const RECORD: &str = include_str!("fixtures/record.json");
+
+fn cassette_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "airtable-cassette-{}-{}.json",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn base(client: CassetteClient) -> airtable::Base<Word> {
+    airtable::new::<Word>("keySecret123", "app123", "Words").http_client(client)
+}
+
+fn session(base: &airtable::Base<Word>) -> (Vec<Word>, Word) {
+    let words: Vec<Word> = base.query().view("To Learn").into_iter().collect();
+    let word = block_on(base.get("rec1")).unwrap();
+    (words, word)
+}
+
+#[test]
+fn recorded_session_replays_without_network() {
+    let path = cassette_path("replay");
+    let client = FakeClient::new();
+    client
+        .respond(200, PAGE_1)
+        .respond(200, PAGE_2)
+        .respond(200, RECORD);
+
+    let recording = CassetteClient::new(&path, client.clone()).unwrap();
+    assert!(!recording.is_replaying());
+    let recorded = session(&base(recording));
+    assert_eq!(client.requests().len(), 3);
+
+    let cassette = std::fs::read_to_string(&path).unwrap();
+    assert!(!cassette.contains("keySecret123"));
+    assert!(cassette.contains(airtable::cassette::REDACTED));
+
+    // Nothing is queued, so any request reaching the inner client would fail.
+    let offline = FakeClient::new();
+    let replaying = CassetteClient::new(&path, offline.clone()).unwrap();
+    assert!(replaying.is_replaying());
+    let replayed = session(&base(replaying));
+
+    assert_eq!(replayed, recorded);
+    assert!(offline.requests().is_empty());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn replay_fails_requests_that_were_not_recorded() {
+    let path = cassette_path("mismatch");
+    let client = FakeClient::new();
+    client.respond(200, RECORD);
+    block_on(base(CassetteClient::record(&path, client)).get("rec1")).unwrap();
+
+    let base = base(CassetteClient::replay(&path).unwrap());
+    let start = std::time::Instant::now();
+    let err = block_on(base.get("rec2")).unwrap_err();
+    assert!(format!("{:#}", err).contains("doesn't match"));
+    assert!(matches!(
+        err.downcast_ref::<airtable::Error>(),
+        Some(airtable::Error::NotRecorded { .. })
+    ));
+    // A mismatch is never retried.
+    assert!(start.elapsed() < std::time::Duration::from_millis(500));
+    std::fs::remove_file(&path).unwrap();
+}
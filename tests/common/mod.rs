@@ -0,0 +1,105 @@
+#![allow(dead_code)]
+
+use airtable::http::{HttpClient, Request, Response};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Word {
+    #[serde(default, skip_serializing)]
+    pub id: airtable::RecordId,
+    #[serde(rename = "Word")]
+    pub word: String,
+    #[serde(rename = "Google", default)]
+    pub google: i64,
+}
+
+airtable::impl_record!(Word => ["Word", "Google"]);
+
+/// Answers requests with queued responses (or failures), in order, and keeps every request it
+/// was sent.
+#[derive(Debug, Clone, Default)]
+pub struct FakeClient {
+    responses: Arc<Mutex<VecDeque<Result<Response, String>>>>,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl FakeClient {
+    pub fn new() -> Self {
+        FakeClient::default()
+    }
+
+    pub fn respond(&self, status: u16, body: &str) -> &Self {
+        self.responses
+            .lock()
+            .unwrap()
+            .push_back(Ok(Response::new(status, body)));
+        self
+    }
+
+    /// Fails the next request without a response, like a timeout would.
+    pub fn fail(&self, message: &str) -> &Self {
+        self.responses
+            .lock()
+            .unwrap()
+            .push_back(Err(message.to_owned()));
+        self
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl HttpClient for FakeClient {
+    fn send(&self, request: Request) -> anyhow::Result<Response> {
+        self.requests.lock().unwrap().push(request);
+        self.responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| Err("no response queued".to_owned()))
+            .map_err(|message| anyhow::anyhow!(message))
+    }
+}
+
+pub fn base(client: &FakeClient) -> airtable::Base<Word> {
+    airtable::new::<Word>("key123", "app123", "Words").http_client(client.clone())
+}
+
+pub fn header<'a>(request: &'a Request, name: &str) -> Option<&'a str> {
+    request
+        .headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+pub fn query<'a>(request: &'a Request, param: &str) -> Vec<&'a str> {
+    request
+        .query
+        .iter()
+        .filter(|(key, _)| key == param)
+        .map(|(_, value)| value.as_str())
+        .collect()
+}
+
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+/// The crate's async methods never actually suspend, so polling once is enough.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut context = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    match future.as_mut().poll(&mut context) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("future unexpectedly suspended"),
+    }
+}
@@ -0,0 +1,210 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use airtable::http::{proxy_bypassed, ConnectionPool, HttpClient, Request, UreqClient};
+use airtable::Endpoints;
+
+// Serves `{}` to every request on a local port, counting the connections opened to it.
+fn serve() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/v0/app123/Words", listener.local_addr().unwrap());
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            counter.fetch_add(1, Ordering::SeqCst);
+            thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                loop {
+                    line.clear();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                        return;
+                    }
+                    if line == "\r\n" {
+                        let response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}";
+                        if stream.write_all(response.as_bytes()).is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    (url, connections)
+}
+
+#[test]
+fn sequential_requests_reuse_one_connection() {
+    let (url, connections) = serve();
+    let client = UreqClient::new();
+
+    for _ in 0..3 {
+        assert_eq!(client.send(Request::new("GET", &url)).unwrap().status, 200);
+    }
+
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn a_disabled_pool_opens_a_connection_per_request() {
+    let (url, connections) = serve();
+    let client = UreqClient::with_connection_pool(ConnectionPool::disabled());
+
+    for _ in 0..3 {
+        assert_eq!(client.send(Request::new("GET", &url)).unwrap().status, 200);
+    }
+
+    assert_eq!(connections.load(Ordering::SeqCst), 3);
+}
+
+// Answers one request with `{}` and returns its request line.
+fn request_line(request: Request) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}{}", listener.local_addr().unwrap(), request.url);
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut first = String::new();
+        reader.read_line(&mut first).unwrap();
+        let mut line = String::new();
+        while line != "\r\n" {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+        }
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}";
+        stream.write_all(response.as_bytes()).unwrap();
+        first
+    });
+
+    let request = Request { url, ..request };
+    UreqClient::new().send(request).unwrap();
+    server.join().unwrap()
+}
+
+#[test]
+fn query_params_are_percent_encoded() {
+    let request = Request::new("GET", "/v0/app123/Words")
+        .query("sort[0][field]", "Days overdue (formula)")
+        .query("sort[1][field]", "Total & tax");
+
+    assert_eq!(
+        request_line(request),
+        "GET /v0/app123/Words?sort%5B0%5D%5Bfield%5D=Days+overdue+%28formula%29\
+         &sort%5B1%5D%5Bfield%5D=Total+%26+tax HTTP/1.1\r\n"
+    );
+}
+
+#[test]
+fn special_characters_in_field_names_and_formulas_are_encoded_once() {
+    let request = Request::new("GET", "/v0/app123/Words")
+        .query("fields[]", "Order #")
+        .query("fields[]", "Prénom")
+        .query("fields[]", "A[B]")
+        .query(
+            "filterByFormula",
+            r#"AND({Order #} = "5%", {A[B]} != 'x+y')"#,
+        );
+
+    assert_eq!(
+        request_line(request),
+        "GET /v0/app123/Words?fields%5B%5D=Order+%23&fields%5B%5D=Pr%C3%A9nom\
+         &fields%5B%5D=A%5BB%5D&filterByFormula=AND%28%7BOrder+%23%7D+%3D+%225%25%22%2C+\
+         %7BA%5BB%5D%7D+%21%3D+%27x%2By%27%29 HTTP/1.1\r\n"
+    );
+}
+
+#[test]
+fn special_characters_in_table_names_stay_in_the_path() {
+    let endpoints = Endpoints::with_root("/v0");
+    let request = Request::new(
+        "GET",
+        &endpoints.record("app123", "Order #/Prénom [A]", "rec1"),
+    );
+
+    assert_eq!(
+        request_line(request),
+        "GET /v0/app123/Order%20%23%2FPr%C3%A9nom%20%5BA%5D/rec1 HTTP/1.1\r\n"
+    );
+}
+
+#[test]
+fn no_proxy_entries_match_the_host_and_its_subdomains() {
+    let host = "api.airtable.com";
+
+    assert!(proxy_bypassed("*", host));
+    assert!(proxy_bypassed("localhost, *", host));
+    assert!(proxy_bypassed("airtable.com", host));
+    assert!(proxy_bypassed(".airtable.com", host));
+    assert!(proxy_bypassed("api.airtable.com", host));
+    assert!(proxy_bypassed("internal.corp,API.Airtable.COM", host));
+    assert!(proxy_bypassed("airtable.com:443", host));
+    assert!(proxy_bypassed("airtable.com", "API.AIRTABLE.COM:443"));
+
+    assert!(!proxy_bypassed("", host));
+    assert!(!proxy_bypassed("notairtable.com", host));
+    assert!(!proxy_bypassed("airtable.com", "notairtable.com"));
+    assert!(!proxy_bypassed("airtable.com", "api.notairtable.com"));
+    assert!(!proxy_bypassed("other.airtable.com", host));
+    assert!(!proxy_bypassed("airtable.co", host));
+}
+
+#[test]
+fn each_endpoint_family_builds_its_url() {
+    let endpoints = Endpoints::default();
+
+    assert_eq!(
+        endpoints.records("app123", "Words"),
+        "https://api.airtable.com/v0/app123/Words"
+    );
+    assert_eq!(
+        endpoints.record("app123", "Words", "rec1"),
+        "https://api.airtable.com/v0/app123/Words/rec1"
+    );
+    assert_eq!(endpoints.bases(), "https://api.airtable.com/v0/meta/bases");
+    assert_eq!(
+        endpoints.tables("app123"),
+        "https://api.airtable.com/v0/meta/bases/app123/tables"
+    );
+    assert_eq!(
+        endpoints.webhooks("app123"),
+        "https://api.airtable.com/v0/bases/app123/webhooks"
+    );
+    assert_eq!(
+        endpoints.webhook_payloads("app123", "ach1"),
+        "https://api.airtable.com/v0/bases/app123/webhooks/ach1/payloads"
+    );
+    assert_eq!(
+        endpoints.upload_attachment("app123", "rec1", "Photos"),
+        "https://content.airtable.com/v0/app123/rec1/Photos/uploadAttachment"
+    );
+    assert_eq!(endpoints.api_host(), "api.airtable.com");
+
+    // Names are a single path segment each, whatever they contain.
+    assert_eq!(
+        endpoints.records("app123", "Word List/2021"),
+        "https://api.airtable.com/v0/app123/Word%20List%2F2021"
+    );
+    assert_eq!(
+        endpoints.record("app123", "To Learn", "rec1"),
+        "https://api.airtable.com/v0/app123/To%20Learn/rec1"
+    );
+    assert_eq!(
+        endpoints.upload_attachment("app123", "rec1", "Scans / PDFs"),
+        "https://content.airtable.com/v0/app123/rec1/Scans%20%2F%20PDFs/uploadAttachment"
+    );
+
+    let mock = Endpoints::with_root("http://localhost:8080/v0/");
+    assert_eq!(mock.bases(), "http://localhost:8080/v0/meta/bases");
+    assert_eq!(
+        mock.upload_attachment("app123", "rec1", "Photos"),
+        "http://localhost:8080/v0/app123/rec1/Photos/uploadAttachment"
+    );
+    assert_eq!(mock.api_host(), "localhost");
+}
@@ -0,0 +1,83 @@
+// In a file of its own: the warning fires once per process, so it can only be seen once.
+mod common;
+
+use common::{block_on, FakeClient, Word};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+// Keeps the message of every warning logged while it's the default subscriber.
+#[derive(Clone, Default)]
+struct Warnings(Arc<Mutex<Vec<String>>>);
+
+impl Visit for Warnings {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0.lock().unwrap().push(format!("{:?}", value));
+        }
+    }
+}
+
+impl Subscriber for Warnings {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() == tracing::Level::WARN
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        event.record(&mut self.clone());
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn deprecated_keys_are_warned_about_once() {
+    const RECORD: &str = include_str!("fixtures/record.json");
+    let current = FakeClient::new();
+    current.respond(200, RECORD);
+    let deprecated = DeprecatedClient(airtable::http::Response {
+        headers: vec![("Deprecation".to_owned(), "true".to_owned())],
+        ..airtable::http::Response::new(200, RECORD)
+    });
+
+    let warnings = Warnings::default();
+    tracing::subscriber::with_default(warnings.clone(), || {
+        let base = airtable::new::<Word>("pat123.secret", "app123", "Words").http_client(current);
+        block_on(base.get("rec1")).unwrap();
+        assert!(warnings.0.lock().unwrap().is_empty());
+
+        let base = airtable::new::<Word>("key123", "app123", "Words").http_client(deprecated);
+        block_on(base.get("rec1")).unwrap();
+        block_on(base.get("rec1")).unwrap();
+    });
+
+    let warnings = warnings.0.lock().unwrap();
+    assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    assert!(
+        warnings[0].contains("personal access token"),
+        "{}",
+        warnings[0]
+    );
+}
+
+// Answers every request with the same response.
+#[derive(Debug)]
+struct DeprecatedClient(airtable::http::Response);
+
+impl airtable::http::HttpClient for DeprecatedClient {
+    fn send(&self, _: airtable::http::Request) -> anyhow::Result<airtable::http::Response> {
+        Ok(self.0.clone())
+    }
+}
@@ -0,0 +1,434 @@
+mod common;
+
+use airtable::fields::Attachment;
+
+#[test]
+fn attachments_deserialize_with_and_without_thumbnails() {
+    let attachments: Vec<Attachment> =
+        serde_json::from_str(include_str!("fixtures/attachments.json")).unwrap();
+
+    let photo = &attachments[0];
+    assert_eq!(photo.filename.as_deref(), Some("photo.jpg"));
+    assert_eq!(photo.mime_type.as_deref(), Some("image/jpeg"));
+    assert_eq!(photo.width, Some(1024));
+    let thumbnails = photo.thumbnails.as_ref().unwrap();
+    assert_eq!(thumbnails.small.as_ref().unwrap().width, Some(48));
+    assert_eq!(
+        thumbnails.large.as_ref().unwrap().url,
+        "https://v5.airtableusercontent.com/v1/abc/large"
+    );
+
+    let notes = &attachments[1];
+    assert_eq!(notes.size, Some(512));
+    assert!(notes.thumbnails.is_none());
+}
+
+#[derive(serde::Deserialize)]
+struct Person {
+    #[serde(
+        default,
+        deserialize_with = "airtable::fields::deserialize_empty_as_none"
+    )]
+    nickname: Option<String>,
+}
+
+#[test]
+fn empty_strings_deserialize_as_none() {
+    let parse = |json: &str| serde_json::from_str::<Person>(json).unwrap().nickname;
+
+    assert_eq!(parse(r#"{ "nickname": "" }"#), None);
+    assert_eq!(parse(r#"{}"#), None);
+    assert_eq!(parse(r#"{ "nickname": null }"#), None);
+    assert_eq!(parse(r#"{ "nickname": "Bob" }"#), Some("Bob".to_owned()));
+}
+
+#[test]
+fn impl_record_lists_field_names() {
+    use airtable::Record;
+
+    assert_eq!(common::Word::field_names(), ["Word", "Google"]);
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+struct Import {
+    #[serde(default, skip_serializing)]
+    id: airtable::RecordId,
+    #[serde(
+        rename = "Count",
+        serialize_with = "airtable::fields::serialize_number_from_str"
+    )]
+    count: String,
+    #[serde(
+        rename = "Price",
+        serialize_with = "airtable::fields::serialize_number_from_str"
+    )]
+    price: String,
+    #[serde(rename = "Added")]
+    added: String,
+    #[serde(rename = "Status")]
+    status: String,
+}
+
+airtable::impl_record!(Import);
+
+#[test]
+fn string_values_import_with_typecast() {
+    let client = common::FakeClient::new();
+    client.respond(200, r#"{ "id": "rec1", "fields": {} }"#);
+    let base = airtable::new::<Import>("key123", "app123", "Imports")
+        .http_client(client.clone())
+        .typecast(true);
+
+    let row = Import {
+        count: " 42 ".to_owned(),
+        price: "9.95".to_owned(),
+        added: "2021-04-01".to_owned(),
+        status: "New".to_owned(),
+        ..Default::default()
+    };
+    common::block_on(base.create(&row)).unwrap();
+
+    let request = &client.requests()[0];
+    let body: serde_json::Value = serde_json::from_str(request.body.as_ref().unwrap()).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "fields": { "Count": 42, "Price": 9.95, "Added": "2021-04-01", "Status": "New" },
+            "typecast": true
+        })
+    );
+}
+
+#[test]
+fn barcodes_and_buttons_round_trip() {
+    use airtable::fields::{Barcode, Button};
+
+    let barcode = serde_json::json!({ "text": "012345678905", "type": "upce" });
+    let parsed: Barcode = serde_json::from_value(barcode.clone()).unwrap();
+    assert_eq!(parsed.symbology.as_deref(), Some("upce"));
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), barcode);
+
+    let typed_in: Barcode = serde_json::from_str(r#"{ "text": "ISBN 0-201-63361-2" }"#).unwrap();
+    assert_eq!(typed_in.symbology, None);
+
+    let button = serde_json::json!({ "label": "Open", "url": "https://example.com/rec1" });
+    let parsed: Button = serde_json::from_value(button.clone()).unwrap();
+    assert_eq!(parsed.url.as_deref(), Some("https://example.com/rec1"));
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), button);
+
+    let script: Button = serde_json::from_str(r#"{ "label": "Run", "url": null }"#).unwrap();
+    assert_eq!(script.url, None);
+}
+
+#[test]
+fn last_modified_times_deserialize_as_system_times() {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[derive(serde::Deserialize)]
+    struct Task {
+        #[serde(rename = "Status modified", default)]
+        #[serde(deserialize_with = "airtable::fields::deserialize_timestamp")]
+        status_modified: Option<SystemTime>,
+        #[serde(rename = "Notes modified", default)]
+        #[serde(deserialize_with = "airtable::fields::deserialize_timestamp")]
+        notes_modified: Option<SystemTime>,
+    }
+
+    let task: Task = serde_json::from_str(
+        r#"{ "Name": "Ship it", "Status": "Done", "Status modified": "2021-04-01T12:34:56.789Z" }"#,
+    )
+    .unwrap();
+    let expected = UNIX_EPOCH + Duration::from_millis(1_617_280_496_789);
+    assert_eq!(task.status_modified, Some(expected));
+    assert_eq!(task.notes_modified, None);
+
+    let old: Task =
+        serde_json::from_str(r#"{ "Status modified": "1969-12-31T23:59:59Z" }"#).unwrap();
+    assert_eq!(
+        old.status_modified,
+        Some(UNIX_EPOCH - Duration::from_secs(1))
+    );
+
+    assert!(serde_json::from_str::<Task>(r#"{ "Status modified": "yesterday" }"#).is_err());
+}
+
+#[test]
+fn durations_and_currencies_round_trip() {
+    use airtable::fields::{Currency, Duration};
+
+    let duration: Duration = serde_json::from_str("3723.5").unwrap();
+    assert_eq!(duration.to_string(), "1:02:03.500");
+    assert_eq!(
+        duration.to_std(),
+        Some(std::time::Duration::from_millis(3_723_500))
+    );
+    assert_eq!(serde_json::to_string(&duration).unwrap(), "3723.5");
+    assert_eq!(Duration(-90.0).to_string(), "-0:01:30");
+    assert_eq!(Duration(-90.0).to_std(), None);
+
+    let price: Currency = serde_json::from_str("-1234.5").unwrap();
+    assert_eq!(price.format("$", 2), "-$1,234.50");
+    assert_eq!(Currency(999.0).format("£", 0), "£999");
+    assert_eq!(Currency(1_000_000.0).format("€", 0), "€1,000,000");
+    assert_eq!(serde_json::to_string(&price).unwrap(), "-1234.5");
+}
+
+#[test]
+fn percents_and_ratings_round_trip() {
+    use airtable::fields::{Percent, Rating};
+
+    let done: Percent = serde_json::from_str("0.755").unwrap();
+    assert_eq!(done.fraction(), 0.755);
+    assert_eq!(done.format(1), "75.5%");
+    assert_eq!(Percent::from_percentage(75.0), Percent(0.75));
+    assert_eq!(
+        serde_json::to_string(&Percent::from_percentage(75.0)).unwrap(),
+        "0.75"
+    );
+
+    let rating: Rating = serde_json::from_str("3").unwrap();
+    assert_eq!(rating.value(), 3);
+    assert_eq!(rating.stars(5), "★★★☆☆");
+    assert_eq!(serde_json::to_string(&rating).unwrap(), "3");
+    rating.validate(5).unwrap();
+    assert!(serde_json::from_str::<Rating>("2.5").is_err());
+
+    for (rating, max) in [(6, 5), (0, 5)] {
+        let err = Rating(rating).validate(max).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<airtable::Error>(),
+            Some(&airtable::Error::RatingOutOfRange { rating, max })
+        );
+    }
+}
+
+#[test]
+fn record_ids_parse_only_record_shaped_strings() {
+    use airtable::RecordId;
+
+    let id: RecordId = "rec8116cdd76088af".parse().unwrap();
+    assert_eq!(id, "rec8116cdd76088af");
+    assert_eq!(id.to_string(), "rec8116cdd76088af");
+    assert_eq!(
+        serde_json::to_string(&id).unwrap(),
+        r#""rec8116cdd76088af""#
+    );
+
+    for invalid in ["app123", "rec", "rec12-3", ""] {
+        assert_eq!(
+            invalid.parse::<RecordId>(),
+            Err(airtable::Error::InvalidRecordId {
+                id: invalid.to_owned()
+            })
+        );
+    }
+}
+
+#[test]
+fn formula_results_deserialize_every_variant() {
+    use airtable::fields::FormulaResult;
+
+    #[derive(serde::Deserialize)]
+    struct Row {
+        #[serde(rename = "Total", default)]
+        total: Option<FormulaResult>,
+    }
+
+    let parse = |json: &str| serde_json::from_str::<Row>(json).unwrap().total;
+
+    assert_eq!(
+        parse(r#"{ "Total": 42.5 }"#),
+        Some(FormulaResult::Number(42.5))
+    );
+    assert_eq!(
+        parse(r#"{ "Total": "overdue" }"#),
+        Some(FormulaResult::Text("overdue".to_owned()))
+    );
+    assert_eq!(
+        parse(r#"{ "Total": true }"#),
+        Some(FormulaResult::Bool(true))
+    );
+    assert_eq!(
+        parse(r#"{ "Total": [1, "two"] }"#),
+        Some(FormulaResult::Array(vec![
+            FormulaResult::Number(1.0),
+            FormulaResult::Text("two".to_owned())
+        ]))
+    );
+    assert_eq!(parse("{}"), None);
+
+    let failed = parse(r##"{ "Total": { "error": "#ERROR!" } }"##).unwrap();
+    assert!(failed.is_error());
+    assert_eq!(failed.error(), Some("#ERROR!"));
+    assert_eq!(failed.as_f64(), None);
+
+    let rows: Vec<Row> =
+        serde_json::from_str(r##"[{ "Total": 3 }, { "Total": { "error": "#DIV/0!" } }]"##).unwrap();
+    assert_eq!(
+        rows[0].total.as_ref().and_then(FormulaResult::as_f64),
+        Some(3.0)
+    );
+    assert_eq!(
+        rows[1].total.as_ref().and_then(FormulaResult::error),
+        Some("#DIV/0!")
+    );
+}
+
+#[test]
+fn exact_numbers_keep_precision_floats_lose() {
+    use airtable::fields::Currency;
+
+    #[derive(serde::Deserialize)]
+    struct Order {
+        #[serde(rename = "Order number")]
+        #[serde(deserialize_with = "airtable::fields::deserialize_exact_i64")]
+        number: i64,
+    }
+
+    let json = r#"{ "Order number": 9007199254740993 }"#;
+    let as_float: serde_json::Value = serde_json::from_str(json).unwrap();
+    let as_float = as_float["Order number"].as_f64().unwrap();
+    assert_ne!(as_float as i64, 9007199254740993);
+    let order: Order = serde_json::from_str(json).unwrap();
+    assert_eq!(order.number, 9007199254740993);
+
+    assert_eq!(
+        serde_json::from_str::<Order>(r#"{ "Order number": 42.0 }"#)
+            .unwrap()
+            .number,
+        42
+    );
+    assert!(serde_json::from_str::<Order>(r#"{ "Order number": 42.5 }"#).is_err());
+    assert!(serde_json::from_str::<Order>(r#"{ "Order number": 1e300 }"#).is_err());
+
+    let price = Currency(19.99);
+    assert_eq!((price.amount() * 100.0) as i64, 1998);
+    assert_eq!(price.minor_units(2), Some(1999));
+    assert_eq!(Currency(-0.1).minor_units(2), Some(-10));
+    assert_eq!(Currency(f64::NAN).minor_units(2), None);
+}
+
+#[test]
+fn collaborators_round_trip_by_id_or_email() {
+    use airtable::fields::Collaborator;
+    use serde_json::json;
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Task {
+        #[serde(rename = "Owner", default)]
+        owner: Option<Collaborator>,
+        #[serde(rename = "Reviewers", default)]
+        reviewers: Vec<Collaborator>,
+    }
+
+    let task: Task = serde_json::from_value(json!({
+        "Owner": { "id": "usrAda", "email": "ada@example.com", "name": "Ada Lovelace" },
+        "Reviewers": [
+            { "id": "usrGrace", "email": "grace@example.com", "name": "Grace Hopper" },
+            { "id": "usrAlan", "email": "alan@example.com", "name": "Alan Turing" }
+        ]
+    }))
+    .unwrap();
+
+    let owner = task.owner.as_ref().unwrap();
+    assert_eq!(owner.name.as_deref(), Some("Ada Lovelace"));
+    assert_eq!(owner.email.as_deref(), Some("ada@example.com"));
+    assert_eq!(task.reviewers[1].id.as_deref(), Some("usrAlan"));
+    assert_eq!(
+        serde_json::to_value(&task).unwrap(),
+        json!({
+            "Owner": { "id": "usrAda" },
+            "Reviewers": [{ "id": "usrGrace" }, { "id": "usrAlan" }]
+        })
+    );
+
+    let assigned = Task {
+        owner: Some(Collaborator::by_email("ada@example.com")),
+        reviewers: vec![Collaborator::by_id("usrGrace")],
+    };
+    assert_eq!(
+        serde_json::to_value(&assigned).unwrap(),
+        json!({
+            "Owner": { "email": "ada@example.com" },
+            "Reviewers": [{ "id": "usrGrace" }]
+        })
+    );
+    assert!(serde_json::to_value(Collaborator::default()).is_err());
+}
+
+#[test]
+fn select_fields_round_trip_and_validate_against_options() {
+    use airtable::fields::{MultiSelect, SingleSelect};
+
+    let options = ["Noun", "Verb", "Adjective"];
+
+    let level: SingleSelect = serde_json::from_str(r#""Verb""#).unwrap();
+    assert_eq!(level.as_str(), "Verb");
+    assert_eq!(serde_json::to_string(&level).unwrap(), r#""Verb""#);
+    level.validate(&options).unwrap();
+
+    let tags: MultiSelect = serde_json::from_str(r#"["Noun", "Adjective"]"#).unwrap();
+    assert!(tags.contains("Adjective"));
+    assert!(!tags.contains("Verb"));
+    assert_eq!(
+        serde_json::to_string(&tags).unwrap(),
+        r#"["Noun","Adjective"]"#
+    );
+    tags.validate(&options).unwrap();
+
+    let typo = SingleSelect("Adverb".to_owned())
+        .validate(&options)
+        .unwrap_err();
+    assert_eq!(
+        typo.downcast_ref::<airtable::Error>(),
+        Some(&airtable::Error::InvalidSelectOption {
+            option: "Adverb".to_owned()
+        })
+    );
+    let typo = MultiSelect(vec!["Noun".to_owned(), "noun".to_owned()])
+        .validate(&options)
+        .unwrap_err();
+    assert_eq!(
+        typo.downcast_ref::<airtable::Error>(),
+        Some(&airtable::Error::InvalidSelectOption {
+            option: "noun".to_owned()
+        })
+    );
+}
+
+#[cfg(feature = "decimal")]
+#[test]
+fn decimal_amounts_add_up_exactly() {
+    use rust_decimal::Decimal;
+
+    #[derive(serde::Deserialize, serde::Serialize)]
+    struct Line {
+        #[serde(
+            deserialize_with = "airtable::fields::deserialize_decimal",
+            serialize_with = "airtable::fields::serialize_decimal"
+        )]
+        amount: Decimal,
+    }
+
+    let parse = |json: &str| serde_json::from_str::<Line>(json).unwrap().amount;
+    let (dime, twenty) = (parse(r#"{ "amount": 0.1 }"#), parse(r#"{ "amount": 0.2 }"#));
+
+    assert_ne!(0.1 + 0.2, 0.3);
+    assert_eq!(dime + twenty, "0.3".parse::<Decimal>().unwrap());
+    assert_eq!(
+        parse(r#"{ "amount": 19.99 }"#) * Decimal::from(100),
+        Decimal::from(1999)
+    );
+    assert_eq!(parse(r#"{ "amount": "12.50" }"#).to_string(), "12.50");
+    assert_eq!(parse(r#"{ "amount": 42 }"#), Decimal::from(42));
+    assert!(serde_json::from_str::<Line>(r#"{ "amount": "twelve" }"#).is_err());
+
+    let json = |amount: &str| {
+        let line = Line {
+            amount: amount.parse().unwrap(),
+        };
+        serde_json::to_string(&line).unwrap()
+    };
+    assert_eq!(json("19.99"), r#"{"amount":19.99}"#);
+    assert_eq!(json("1999"), r#"{"amount":1999}"#);
+}
@@ -0,0 +1,104 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use airtable::formula;
+
+#[test]
+fn between_dates_formats_both_bounds_in_utc() {
+    let start = UNIX_EPOCH + Duration::from_secs(1_617_235_200);
+    let end = start + Duration::from_secs(7 * 24 * 60 * 60);
+
+    assert_eq!(
+        formula::between_dates("Last {Edit}", start, end),
+        concat!(
+            r#"AND(IS_AFTER({Last {Edit\}}, DATETIME_PARSE("2021-04-01T00:00:00.000Z")), "#,
+            r#"IS_BEFORE({Last {Edit\}}, DATETIME_PARSE("2021-04-08T00:00:00.000Z")))"#
+        )
+    );
+}
+
+#[test]
+fn created_time_helpers_compare_against_now() {
+    assert_eq!(formula::CREATED_TIME, "CREATED_TIME()");
+    assert_eq!(
+        formula::created_in_last_days(7),
+        "IS_AFTER(CREATED_TIME(), DATEADD(NOW(), -7, 'days'))"
+    );
+    assert_eq!(
+        formula::created_before_days_ago(30),
+        "IS_BEFORE(CREATED_TIME(), DATEADD(NOW(), -30, 'days'))"
+    );
+}
+
+#[test]
+fn bind_renders_each_value_type() {
+    use formula::FormulaValue;
+    let bind = |template, args: &[FormulaValue]| formula::bind(template, args).unwrap();
+
+    assert_eq!(
+        bind(
+            "AND({Done} = ?, {Pinned} = ?)",
+            &[true.into(), false.into()]
+        ),
+        "AND({Done} = TRUE(), {Pinned} = FALSE())"
+    );
+    assert_eq!(
+        bind("{Google} > ?", &[6870000.into()]),
+        "{Google} > 6870000"
+    );
+    assert_eq!(bind("{Ratio} < ?", &[0.25.into()]), "{Ratio} < 0.25");
+
+    let noon = UNIX_EPOCH + Duration::from_millis(1_617_278_400_250);
+    assert_eq!(
+        bind("IS_AFTER({Due}, ?)", &[FormulaValue::Date(noon)]),
+        r#"IS_AFTER({Due}, DATETIME_PARSE("2021-04-01T12:00:00.250Z"))"#
+    );
+
+    assert_eq!(
+        bind("{Path} = ?", &[r#"C:\notes\"draft""#.into()]),
+        r#"{Path} = "C:\\notes\\\"draft\"""#
+    );
+}
+
+#[test]
+fn bind_leaves_question_marks_in_quoted_literals_alone() {
+    assert_eq!(
+        formula::bind(
+            r#"AND({Title} = "Why?", {Tag} = 'a?b', {Word} = ?)"#,
+            &["lurid".into()]
+        )
+        .unwrap(),
+        r#"AND({Title} = "Why?", {Tag} = 'a?b', {Word} = "lurid")"#
+    );
+    assert_eq!(
+        formula::bind(r#"{Title} = "say \"?\"" & ?"#, &["!".into()]).unwrap(),
+        r#"{Title} = "say \"?\"" & "!""#
+    );
+}
+
+#[test]
+fn bind_rejects_mismatched_arguments_and_non_finite_numbers() {
+    let reason = |template, args: &[formula::FormulaValue]| {
+        let err = formula::bind(template, args).unwrap_err();
+        match err.downcast_ref::<airtable::Error>() {
+            Some(airtable::Error::InvalidFormula { reason }) => reason.clone(),
+            other => panic!("unexpected error {:?}", other),
+        }
+    };
+
+    assert_eq!(
+        reason("AND({A} = ?, {B} = ?)", &[1.into()]),
+        "the template takes 2 arguments, not 1"
+    );
+    assert_eq!(
+        reason("{A} = ?", &[1.into(), 2.into()]),
+        "the template takes 1 arguments, not 2"
+    );
+    assert_eq!(
+        reason(r#"{A} = "?""#, &[1.into()]),
+        "the template takes 0 arguments, not 1"
+    );
+    for number in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let reason = reason("{Ratio} < ?", &[number.into()]);
+        assert!(reason.contains("formulas can't express"), "{}", reason);
+    }
+}
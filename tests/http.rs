@@ -0,0 +1,1738 @@
+mod common;
+
+use common::{base, block_on, header, query, FakeClient, Word};
+use serde_json::json;
+
+const PAGE_1: &str = include_str!("fixtures/page_1.json");
+const PAGE_2: &str = include_str!("fixtures/page_2.json");
+const RECORD: &str = include_str!("fixtures/record.json");
+const ERROR: &str = include_str!("fixtures/error_invalid_value.json");
+
+fn lurid() -> Word {
+    Word {
+        id: "rec1".into(),
+        word: "lurid".to_owned(),
+        google: 6870000,
+    }
+}
+
+#[test]
+fn create_posts_fields_with_auth() {
+    let client = FakeClient::new();
+    client.respond(200, RECORD);
+
+    block_on(base(&client).create(&lurid())).unwrap();
+
+    let requests = client.requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.url, "https://api.airtable.com/v0/app123/Words");
+    assert_eq!(header(request, "Authorization"), Some("Bearer key123"));
+    assert_eq!(header(request, "Content-Type"), Some("application/json"));
+
+    let body: serde_json::Value = serde_json::from_str(request.body.as_ref().unwrap()).unwrap();
+    assert_eq!(
+        body,
+        json!({ "fields": { "Word": "lurid", "Google": 6870000 } })
+    );
+}
+
+#[derive(serde::Serialize)]
+struct NewWord<'a> {
+    #[serde(rename = "Word")]
+    word: &'a str,
+}
+
+#[test]
+fn create_as_writes_one_type_and_reads_another() {
+    let client = FakeClient::new();
+    client.respond(200, RECORD);
+
+    let created = block_on(base(&client).create_as(&NewWord { word: "lurid" })).unwrap();
+    assert_eq!(created, lurid());
+
+    let body: serde_json::Value =
+        serde_json::from_str(client.requests()[0].body.as_ref().unwrap()).unwrap();
+    assert_eq!(body, json!({ "fields": { "Word": "lurid" } }));
+}
+
+#[test]
+fn canonical_json_bodies_are_byte_stable() {
+    let client = FakeClient::new();
+    client.respond(200, RECORD).respond(200, RECORD);
+    let base = base(&client).canonical_json().typecast(true);
+
+    let fields: std::collections::HashMap<String, i64> = ["Word", "Google", "Note", "Tag"]
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.to_string(), i as i64))
+        .collect();
+    block_on(base.create_as(&fields)).unwrap();
+    block_on(base.create_as(&fields.clone())).unwrap();
+
+    let requests = client.requests();
+    let expected = r#"{"fields":{"Google":1,"Note":2,"Tag":3,"Word":0},"typecast":true}"#;
+    assert_eq!(requests[0].body.as_deref(), Some(expected));
+    assert_eq!(requests[1].body.as_deref(), Some(expected));
+}
+
+#[test]
+fn create_raw_posts_arbitrary_fields() {
+    let client = FakeClient::new();
+    client.respond(200, RECORD);
+    let base = base(&client);
+
+    let fields = json!({ "Word": "lurid", "Google": 6870000 });
+    let created = block_on(base.create_raw(&fields)).unwrap();
+    assert_eq!(created["id"], "rec1");
+
+    let body: serde_json::Value =
+        serde_json::from_str(client.requests()[0].body.as_ref().unwrap()).unwrap();
+    assert_eq!(body, json!({ "fields": fields }));
+
+    assert!(block_on(base.create_raw(&json!(["lurid"]))).is_err());
+    assert_eq!(client.requests().len(), 1);
+}
+
+#[test]
+fn futures_run_on_any_thread_without_a_runtime() {
+    fn assert_send<F: std::future::Future + Send>(future: F) -> F {
+        future
+    }
+
+    let client = FakeClient::new();
+    client.respond(200, RECORD);
+    let base = base(&client);
+
+    let future = assert_send(base.get("rec1"));
+    let word = std::thread::scope(|scope| scope.spawn(|| block_on(future)).join().unwrap());
+
+    assert_eq!(word.unwrap(), lurid());
+}
+
+#[test]
+fn update_patches_the_record_url() {
+    let client = FakeClient::new();
+    client.respond(200, RECORD);
+
+    block_on(base(&client).update(&lurid())).unwrap();
+
+    let request = &client.requests()[0];
+    assert_eq!(request.method, "PATCH");
+    assert_eq!(request.url, "https://api.airtable.com/v0/app123/Words/rec1");
+    assert_eq!(header(request, "Authorization"), Some("Bearer key123"));
+
+    let body: serde_json::Value = serde_json::from_str(request.body.as_ref().unwrap()).unwrap();
+    assert_eq!(
+        body,
+        json!({ "fields": { "Word": "lurid", "Google": 6870000 } })
+    );
+}
+
+#[test]
+fn record_url_encodes_the_table_name() {
+    let base = airtable::new::<Word>("key123", "app123", "To Learn");
+
+    assert_eq!(
+        base.record_url("rec1"),
+        "https://airtable.com/app123/To%20Learn/rec1"
+    );
+}
+
+#[test]
+fn update_with_a_partial_model_only_sends_its_fields() {
+    #[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+    struct Headword {
+        #[serde(default, skip_serializing)]
+        id: airtable::RecordId,
+        #[serde(rename = "Word")]
+        word: String,
+        #[serde(rename = "Note", default, skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+    }
+    airtable::impl_record!(Headword);
+
+    let client = FakeClient::new();
+    client.respond(
+        200,
+        r#"{ "id": "rec1", "fields": { "Word": "lurid", "Google": 6870000, "Next": true } }"#,
+    );
+    let base = airtable::new::<Headword>("key123", "app123", "Words").http_client(client.clone());
+    let headword = Headword {
+        id: "rec1".into(),
+        word: "lurid".to_owned(),
+        note: None,
+    };
+
+    let updated = block_on(base.update_by_id("rec1", &headword)).unwrap();
+
+    // Columns the struct doesn't model, and the unset note, aren't sent, so Airtable's PATCH
+    // leaves them as they are rather than clearing them.
+    let request = &client.requests()[0];
+    assert_eq!(request.method, "PATCH");
+    let body: serde_json::Value = serde_json::from_str(request.body.as_ref().unwrap()).unwrap();
+    assert_eq!(body, json!({ "fields": { "Word": "lurid" } }));
+    assert_eq!(updated.id, "rec1");
+    assert_eq!(updated.word, "lurid");
+}
+
+#[test]
+fn request_hooks_add_headers_but_not_authorization() {
+    let client = FakeClient::new();
+    client.respond(200, RECORD);
+
+    let base = base(&client).on_request(|request| {
+        assert_eq!(request.method(), "GET");
+        assert!(request.url().ends_with("/Words/rec1"));
+        assert!(request
+            .add_header("authorization", "Bearer stolen")
+            .is_err());
+        request.add_header("X-Correlation-Id", "job-42")
+    });
+    block_on(base.get("rec1")).unwrap();
+
+    let request = &client.requests()[0];
+    assert_eq!(header(request, "X-Correlation-Id"), Some("job-42"));
+    let auth: Vec<_> = request
+        .headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("Authorization"))
+        .collect();
+    assert_eq!(auth.len(), 1);
+    assert_eq!(auth[0].1, "Bearer key123");
+}
+
+#[test]
+fn update_without_an_id_fails_before_sending() {
+    let client = FakeClient::new();
+    let unsaved = Word {
+        id: Default::default(),
+        ..lurid()
+    };
+
+    let err = block_on(base(&client).update(&unsaved)).unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<airtable::Error>(),
+        Some(&airtable::Error::MissingId)
+    );
+    assert!(client.requests().is_empty());
+}
+
+#[test]
+fn get_formatted_sends_the_format_options() {
+    let client = FakeClient::new();
+    client.respond(200, RECORD);
+
+    let format = airtable::FormatOptions {
+        cell_format: airtable::CellFormat::String,
+        time_zone: Some("Europe/London".to_owned()),
+        user_locale: Some("en-gb".to_owned()),
+    };
+    block_on(base(&client).get_formatted("rec1", &format)).unwrap();
+
+    let request = &client.requests()[0];
+    assert_eq!(request.url, "https://api.airtable.com/v0/app123/Words/rec1");
+    assert_eq!(query(request, "cellFormat"), ["string"]);
+    assert_eq!(query(request, "timeZone"), ["Europe/London"]);
+    assert_eq!(query(request, "userLocale"), ["en-gb"]);
+}
+
+#[test]
+fn update_if_patches_when_the_predicate_passes() {
+    let client = FakeClient::new();
+    client.respond(200, RECORD).respond(200, RECORD);
+
+    let updated = block_on(base(&client).update_if(&lurid(), |current| current.google > 0));
+
+    assert_eq!(updated.unwrap(), lurid());
+    let requests = client.requests();
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[1].method, "PATCH");
+}
+
+#[test]
+fn update_if_leaves_the_record_alone_when_the_predicate_fails() {
+    let client = FakeClient::new();
+    client.respond(200, RECORD);
+
+    let err =
+        block_on(base(&client).update_if(&lurid(), |current| current.google == 0)).unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<airtable::Error>(),
+        Some(&airtable::Error::PreconditionFailed {
+            id: "rec1".to_owned()
+        })
+    );
+    assert_eq!(client.requests().len(), 1);
+}
+
+#[test]
+fn append_to_field_merges_linked_records() {
+    let client = FakeClient::new();
+    let linked = |ids: &[&str]| {
+        json!({ "id": "rec1", "fields": { "Word": "lurid", "Synonyms": ids } }).to_string()
+    };
+    client
+        .respond(200, &linked(&["recA", "recB"]))
+        .respond(200, &linked(&["recA", "recB", "recC"]));
+
+    let updated = block_on(base(&client).append_to_field("rec1", "Synonyms", &["recB", "recC"]));
+
+    assert_eq!(updated.unwrap().word, "lurid");
+    let requests = client.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[1].method, "PATCH");
+    let body: serde_json::Value = serde_json::from_str(requests[1].body.as_ref().unwrap()).unwrap();
+    assert_eq!(
+        body,
+        json!({ "fields": { "Synonyms": ["recA", "recB", "recC"] } })
+    );
+}
+
+#[test]
+fn append_to_field_retries_when_another_write_drops_the_values() {
+    let client = FakeClient::new();
+    let linked = |ids: &[&str]| {
+        json!({ "id": "rec1", "fields": { "Word": "lurid", "Synonyms": ids } }).to_string()
+    };
+    client
+        .respond(200, &linked(&["recA"]))
+        .respond(200, &linked(&["recA", "recC"]))
+        .respond(200, &linked(&["recA", "recD"]))
+        .respond(200, &linked(&["recA", "recD", "recC"]))
+        .respond(200, &linked(&["recA", "recD", "recC"]));
+
+    block_on(base(&client).append_to_field_retrying("rec1", "Synonyms", &["recC"], 1)).unwrap();
+
+    let requests = client.requests();
+    let methods: Vec<_> = requests.iter().map(|r| r.method.as_str()).collect();
+    assert_eq!(methods, ["GET", "PATCH", "GET", "PATCH", "GET"]);
+    let body: serde_json::Value = serde_json::from_str(requests[3].body.as_ref().unwrap()).unwrap();
+    assert_eq!(body["fields"]["Synonyms"], json!(["recA", "recD", "recC"]));
+
+    let client = FakeClient::new();
+    client
+        .respond(200, &linked(&[]))
+        .respond(200, &linked(&["recC"]))
+        .respond(200, &linked(&["recD"]))
+        .respond(200, &linked(&["recD", "recC"]))
+        .respond(200, &linked(&["recE"]));
+    let err = block_on(base(&client).append_to_field_retrying("rec1", "Synonyms", &["recC"], 1))
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<airtable::Error>(),
+        Some(&airtable::Error::ConcurrentWrite {
+            id: "rec1".to_owned()
+        })
+    );
+}
+
+#[test]
+fn query_follows_the_offset_across_pages() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1).respond(200, PAGE_2);
+
+    let base = base(&client);
+    let words: Vec<Word> = base.query().view("To Learn").into_iter().collect();
+
+    let ids: Vec<&str> = words.iter().map(|word| word.id.as_str()).collect();
+    assert_eq!(ids, ["rec1", "rec2", "rec3"]);
+    assert_eq!(words[2].word, "obdurate");
+
+    let requests = client.requests();
+    assert_eq!(requests.len(), 2);
+    for request in &requests {
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.url, "https://api.airtable.com/v0/app123/Words");
+        assert_eq!(query(request, "view"), ["To Learn"]);
+    }
+    assert_eq!(query(&requests[1], "offset"), ["itrNext/rec2"]);
+}
+
+#[test]
+fn distinct_values_flattens_and_removes_duplicates() {
+    let client = FakeClient::new();
+    client
+        .respond(
+            200,
+            r#"{ "records": [
+                { "id": "rec1", "fields": { "Tags": ["noun", "rare"] } },
+                { "id": "rec2", "fields": {} },
+                { "id": "rec3", "fields": { "Tags": ["rare"] } }
+            ], "offset": "itrNext/rec3" }"#,
+        )
+        .respond(
+            200,
+            r#"{ "records": [{ "id": "rec4", "fields": { "Tags": ["verb", "noun"] } }] }"#,
+        );
+
+    let base = base(&client);
+    let tags = base
+        .query()
+        .view("To Learn")
+        .distinct_values("Tags")
+        .unwrap();
+
+    assert_eq!(tags, ["noun", "rare", "verb"]);
+    let requests = client.requests();
+    assert_eq!(requests.len(), 2);
+    for request in &requests {
+        assert_eq!(query(request, "fields[]"), ["Tags"]);
+        assert_eq!(query(request, "view"), ["To Learn"]);
+    }
+    assert_eq!(query(&requests[1], "offset"), ["itrNext/rec3"]);
+}
+
+#[test]
+fn distinct_values_of_a_single_value_field() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1).respond(200, PAGE_2);
+
+    let counts = block_on(base(&client).distinct_values("Google")).unwrap();
+
+    assert_eq!(counts, ["6870000", "3100000", "1200000"]);
+}
+
+// A page of ten records, numbered from `first`, ending with `offset`.
+fn page_of_ten(first: usize, offset: &str) -> String {
+    let records: Vec<_> = (first..first + 10)
+        .map(|i| json!({ "id": format!("rec{}", i), "fields": { "Word": format!("word{}", i) } }))
+        .collect();
+    json!({ "records": records, "offset": offset }).to_string()
+}
+
+#[test]
+fn max_records_stops_without_fetching_past_the_cap() {
+    let client = FakeClient::new();
+    client
+        .respond(200, &page_of_ten(1, "itrNext/rec10"))
+        .respond(200, &page_of_ten(11, "itrNext/rec20"));
+
+    let words = base(&client)
+        .query()
+        .max_records(20)
+        .page_size(10)
+        .collect_all()
+        .unwrap();
+
+    assert_eq!(words.len(), 20);
+    assert_eq!(words[19].id, "rec20");
+    assert_eq!(client.requests().len(), 2);
+}
+
+#[test]
+fn a_view_without_a_sort_keeps_the_view_order() {
+    let client = FakeClient::new();
+    client.respond(
+        200,
+        r#"{ "records": [
+            { "id": "rec3", "fields": { "Word": "obdurate" } },
+            { "id": "rec1", "fields": { "Word": "lurid" } },
+            { "id": "rec2", "fields": { "Word": "sanguine" } }
+        ] }"#,
+    );
+
+    let words = base(&client)
+        .query()
+        .view("By Difficulty")
+        .collect_all()
+        .unwrap();
+
+    let ids: Vec<&str> = words.iter().map(|word| word.id.as_str()).collect();
+    assert_eq!(ids, ["rec3", "rec1", "rec2"]);
+    let request = &client.requests()[0];
+    assert_eq!(query(request, "view"), ["By Difficulty"]);
+    assert!(request
+        .query
+        .iter()
+        .all(|(param, _)| !param.starts_with("sort[")));
+}
+
+#[test]
+fn peek_looks_across_the_page_boundary() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1).respond(200, PAGE_2);
+
+    let base = base(&client);
+    let mut words = base.query().into_iter();
+
+    assert_eq!(words.peek().unwrap().id, "rec1");
+    assert_eq!(words.peek().unwrap().id, "rec1");
+    assert_eq!(client.requests().len(), 1);
+    words.next();
+    assert_eq!(words.next().unwrap().id, "rec2");
+    assert_eq!(client.requests().len(), 1);
+
+    assert_eq!(words.peek().unwrap().word, "obdurate");
+    assert_eq!(client.requests().len(), 2);
+    assert_eq!(words.next().unwrap().id, "rec3");
+    assert!(words.peek().is_none());
+    assert!(words.next().is_none());
+    assert_eq!(client.requests().len(), 2);
+}
+
+#[test]
+fn collect_map_keys_records_across_pages() {
+    use airtable::DuplicateKeys;
+
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1).respond(200, PAGE_2);
+    let words = base(&client)
+        .query()
+        .collect_map(|word| word.word.clone(), DuplicateKeys::Error)
+        .unwrap();
+    assert_eq!(words.len(), 3);
+    assert_eq!(words["obdurate"].id, "rec3");
+
+    // "sanguine" and "obdurate" are both eight letters long.
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1).respond(200, PAGE_2);
+    let by_length = base(&client)
+        .query()
+        .collect_map(|word| word.word.len(), DuplicateKeys::LastWins)
+        .unwrap();
+    assert_eq!(by_length.len(), 2);
+    assert_eq!(by_length[&8].word, "obdurate");
+
+    client.respond(200, PAGE_1).respond(200, PAGE_2);
+    let err = base(&client)
+        .query()
+        .collect_map(|word| word.word.len(), DuplicateKeys::Error)
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<airtable::Error>(),
+        Some(&airtable::Error::DuplicateKey {
+            key: "8".to_owned(),
+            ids: vec!["rec2".to_owned(), "rec3".to_owned()]
+        })
+    );
+}
+
+#[test]
+fn group_by_buckets_records_across_pages_in_order() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1).respond(200, PAGE_2);
+    let by_length = base(&client)
+        .query()
+        .group_by(|word| word.word.len())
+        .unwrap();
+
+    assert_eq!(by_length.len(), 2);
+    let ids = |length| -> Vec<&str> {
+        by_length[&length]
+            .iter()
+            .map(|word| word.id.as_str())
+            .collect()
+    };
+    assert_eq!(ids(5), ["rec1"]);
+    assert_eq!(ids(8), ["rec2", "rec3"]);
+
+    // The first page's records aren't returned without the rest.
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1).respond(422, ERROR);
+    assert!(base(&client)
+        .query()
+        .group_by(|word| word.word.len())
+        .is_err());
+}
+
+#[test]
+fn sorts_on_computed_fields_are_sent_by_name() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_2);
+
+    base(&client)
+        .query()
+        .sort(
+            "Days overdue (formula)",
+            airtable::SortDirection::Descending,
+        )
+        .sort("Total & tax", airtable::SortDirection::Ascending)
+        .collect_all()
+        .unwrap();
+
+    let request = &client.requests()[0];
+    assert_eq!(query(request, "sort[0][field]"), ["Days overdue (formula)"]);
+    assert_eq!(query(request, "sort[0][direction]"), ["desc"]);
+    assert_eq!(query(request, "sort[1][field]"), ["Total & tax"]);
+    assert_eq!(query(request, "sort[1][direction]"), ["asc"]);
+}
+
+#[test]
+fn fetch_page_returns_the_next_offset() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1).respond(200, PAGE_2);
+    let base = base(&client);
+    let words = base.query().view("To Learn");
+
+    let (first, offset) = words.fetch_page(None).unwrap();
+    assert_eq!(first.len(), 2);
+    assert_eq!(offset.as_deref(), Some("itrNext/rec2"));
+
+    let (second, offset) = words.fetch_page(offset.as_deref()).unwrap();
+    assert_eq!(second[0].id, "rec3");
+    assert_eq!(offset, None);
+
+    let requests = client.requests();
+    assert_eq!(query(&requests[0], "offset"), [""]);
+    assert_eq!(query(&requests[1], "offset"), ["itrNext/rec2"]);
+    assert_eq!(query(&requests[1], "view"), ["To Learn"]);
+}
+
+#[test]
+fn in_base_targets_another_base_with_the_same_config() {
+    let client = FakeClient::new();
+    client
+        .respond(200, RECORD)
+        .respond(200, RECORD)
+        .respond(200, RECORD);
+    let base = base(&client)
+        .header("X-Team", "words")
+        .unwrap()
+        .cache_records(10, std::time::Duration::from_secs(60));
+    block_on(base.get("rec1")).unwrap();
+
+    let other = base.in_base("app456");
+    block_on(other.get("rec1")).unwrap();
+    block_on(base.get("rec1")).unwrap();
+
+    let requests = client.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        requests[1].url,
+        "https://api.airtable.com/v0/app456/Words/rec1"
+    );
+    assert_eq!(header(&requests[1], "Authorization"), Some("Bearer key123"));
+    assert_eq!(header(&requests[1], "X-Team"), Some("words"));
+}
+
+#[test]
+fn get_many_splits_long_id_lists_across_queries() {
+    let client = FakeClient::new();
+    for _ in 0..4 {
+        client.respond(200, PAGE_2);
+    }
+    let ids: Vec<String> = (0..200).map(|i| format!("rec{:014}", i)).collect();
+
+    let records = block_on(base(&client).get_many(&ids)).unwrap();
+
+    assert_eq!(records.len(), 4);
+    let requests = client.requests();
+    assert_eq!(requests.len(), 4);
+    for (request, chunk) in requests.iter().zip(ids.chunks(50)) {
+        let formula = query(request, "filterByFormula")[0];
+        assert_eq!(formula.matches("RECORD_ID()").count(), 50);
+        assert!(formula.contains(&format!("\"{}\"", chunk[49])));
+    }
+}
+
+#[test]
+fn with_ids_pairs_each_record_with_its_id() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1).respond(200, PAGE_2);
+
+    let pairs: Vec<_> = base(&client).query().into_iter().with_ids().collect();
+
+    let ids: Vec<&str> = pairs.iter().map(|(id, _)| id.as_str()).collect();
+    assert_eq!(ids, ["rec1", "rec2", "rec3"]);
+    assert!(pairs.iter().all(|(id, word)| *id == word.id));
+}
+
+// Answers by the requested view, since views read concurrently can ask in any order.
+#[derive(Debug, Clone, Default)]
+struct ViewClient {
+    requests: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+impl airtable::http::HttpClient for ViewClient {
+    fn send(&self, request: airtable::http::Request) -> anyhow::Result<airtable::http::Response> {
+        let view = query(&request, "view")[0].to_owned();
+        self.requests.lock().unwrap().push(view.clone());
+        match view.as_str() {
+            "Active" => Ok(airtable::http::Response::new(
+                200,
+                include_str!("fixtures/view_active.json"),
+            )),
+            "Flagged" => Ok(airtable::http::Response::new(
+                200,
+                include_str!("fixtures/view_flagged.json"),
+            )),
+            _ => Ok(airtable::http::Response::new(
+                404,
+                r#"{"error": "NOT_FOUND"}"#,
+            )),
+        }
+    }
+}
+
+#[test]
+fn fetch_views_returns_each_record_once() {
+    let client = ViewClient::default();
+    let base = airtable::new::<Word>("key123", "app123", "Words").http_client(client.clone());
+
+    let words = block_on(base.fetch_views(&["Flagged", "Active", "Flagged"])).unwrap();
+
+    let ids: Vec<&str> = words.iter().map(|word| word.id.as_str()).collect();
+    assert_eq!(ids, ["rec2", "rec3", "rec1"]);
+    let mut views = client.requests.lock().unwrap().clone();
+    views.sort();
+    assert_eq!(views, ["Active", "Flagged"]);
+
+    assert!(block_on(base.fetch_views(&["Active", "Missing"])).is_err());
+}
+
+#[test]
+fn record_ids_skip_every_field() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1).respond(200, PAGE_2);
+
+    let ids = base(&client)
+        .query()
+        .formula("{Google} > 0")
+        .record_ids()
+        .unwrap();
+
+    assert_eq!(ids, ["rec1", "rec2", "rec3"]);
+    let requests = client.requests();
+    assert_eq!(query(&requests[0], "fields[]"), [""]);
+    assert_eq!(query(&requests[1], "filterByFormula"), ["{Google} > 0"]);
+    assert_eq!(query(&requests[1], "offset"), ["itrNext/rec2"]);
+}
+
+#[test]
+fn invalid_queries_fail_before_sending() {
+    use airtable::{CellFormat, FormatOptions, SortDirection};
+
+    let client = FakeClient::new();
+    let base = base(&client);
+    let string_format = FormatOptions {
+        cell_format: CellFormat::String,
+        time_zone: Some("Europe/London".to_owned()),
+        user_locale: None,
+    };
+    let queries = vec![
+        base.query().page_size(101),
+        base.query().page_size(0),
+        base.query().max_records(0),
+        base.query().sort(" ", SortDirection::Ascending),
+        base.query().format(string_format.clone()),
+    ];
+
+    for query in queries {
+        let err = query.collect_all().unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<airtable::Error>(),
+                Some(airtable::Error::InvalidQuery { .. })
+            ),
+            "{}",
+            err
+        );
+    }
+    assert!(base.query().page_size(0).record_ids().is_err());
+    assert!(base.query().max_records(0).pages().next().unwrap().is_err());
+    assert!(block_on(base.get_formatted("rec1", &string_format)).is_err());
+    assert!(client.requests().is_empty());
+}
+
+#[test]
+fn cached_queries_are_served_without_refetching() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1).respond(200, PAGE_2);
+    let base = base(&client).cache_queries(10, std::time::Duration::from_secs(60));
+
+    let first = base.query().view("All").collect_all().unwrap();
+    let second = base.query().view("All").collect_all().unwrap();
+    assert_eq!(first, second);
+    assert_eq!(first.len(), 3);
+    assert_eq!(client.requests().len(), 2);
+
+    // A different query misses.
+    client.respond(200, PAGE_2);
+    assert_eq!(base.query().view("Other").collect_all().unwrap().len(), 1);
+    assert_eq!(client.requests().len(), 3);
+
+    // Any write empties the cache.
+    client.respond(200, RECORD).respond(200, PAGE_2);
+    block_on(base.update(&lurid())).unwrap();
+    assert_eq!(base.query().view("All").collect_all().unwrap().len(), 1);
+    assert_eq!(client.requests().len(), 5);
+}
+
+#[test]
+fn cached_queries_expire_after_their_ttl() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_2).respond(200, PAGE_2);
+    let base = base(&client).cache_queries(10, std::time::Duration::from_millis(20));
+
+    base.query().collect_all().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(30));
+    base.query().collect_all().unwrap();
+
+    assert_eq!(client.requests().len(), 2);
+}
+
+#[test]
+fn count_up_to_stops_once_it_reaches_the_limit() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1).respond(200, PAGE_2);
+
+    assert_eq!(block_on(base(&client).count_up_to(2)).unwrap(), 2);
+
+    // The first page already holds two records, so its offset is never followed.
+    let requests = client.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(query(&requests[0], "maxRecords"), ["2"]);
+    assert_eq!(query(&requests[0], "fields[]"), [""]);
+
+    assert_eq!(base(&client).query().count_up_to(5).unwrap(), 1);
+}
+
+#[test]
+fn get_many_aligned_marks_missing_ids() {
+    let client = FakeClient::new();
+    let mut page: serde_json::Value = serde_json::from_str(PAGE_1).unwrap();
+    page.as_object_mut().unwrap().remove("offset");
+    client.respond(200, &page.to_string());
+
+    let found = block_on(base(&client).get_many_aligned(&["rec2", "recGone", "rec1"])).unwrap();
+
+    let ids: Vec<Option<&str>> = found
+        .iter()
+        .map(|word| word.as_ref().map(|w| w.id.as_str()))
+        .collect();
+    assert_eq!(ids, [Some("rec2"), None, Some("rec1")]);
+}
+
+#[test]
+fn error_responses_fail_the_request() {
+    let client = FakeClient::new();
+    client.respond(422, ERROR);
+
+    assert!(block_on(base(&client).create(&lurid())).is_err());
+}
+
+#[test]
+fn missing_required_fields_are_named() {
+    let client = FakeClient::new();
+    client
+        .respond(422, include_str!("fixtures/error_missing_fields.json"))
+        .respond(422, ERROR);
+    let base = base(&client);
+
+    let err = block_on(base.create(&Word::default())).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<airtable::Error>(),
+        Some(&airtable::Error::MissingRequiredFields {
+            fields: vec!["Word".to_owned(), "Google".to_owned()]
+        })
+    );
+
+    let err = block_on(base.create(&lurid())).unwrap_err();
+    assert_eq!(err.downcast_ref::<airtable::Error>(), None);
+}
+
+#[test]
+fn missing_tables_and_records_fail_differently() {
+    let client = FakeClient::new();
+    client
+        .respond(404, include_str!("fixtures/error_table_not_found.json"))
+        .respond(404, include_str!("fixtures/error_not_found.json"));
+    let base = base(&client);
+
+    let err = block_on(base.get("rec1")).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<airtable::Error>(),
+        Some(&airtable::Error::TableNotFound {
+            table: "Words".to_owned()
+        })
+    );
+
+    let err = block_on(base.get("rec1")).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<airtable::Error>(),
+        Some(&airtable::Error::RecordNotFound {
+            id: "rec1".to_owned()
+        })
+    );
+}
+
+#[test]
+fn bursts_are_paced_to_the_rate_limit() {
+    let client = StampingClient::default();
+    for _ in 0..10 {
+        client.inner.respond(200, RECORD);
+    }
+    let base = airtable::new::<Word>("key123", "app123", "Words")
+        .http_client(client.clone())
+        .with_rate_limit(4.0);
+
+    let start = std::time::Instant::now();
+    for _ in 0..10 {
+        block_on(base.get("rec1")).unwrap();
+    }
+    // The first four go out at once, the rest as the second they started rolls forward.
+    assert!(start.elapsed() >= std::time::Duration::from_millis(1950));
+    assert_at_most_per_second(&client.sent.lock().unwrap(), 4);
+}
+
+// Fails every request the way a client enforcing the response size limit does.
+#[derive(Debug, Clone, Default)]
+struct OversizedClient {
+    sent: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+impl airtable::http::HttpClient for OversizedClient {
+    fn send(&self, _: airtable::http::Request) -> anyhow::Result<airtable::http::Response> {
+        self.sent.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Err(airtable::Error::ResponseTooLarge { limit: 10 }.into())
+    }
+}
+
+#[test]
+fn oversized_responses_are_not_retried() {
+    let client = OversizedClient::default();
+    let base = airtable::new::<Word>("key123", "app123", "Words").http_client(client.clone());
+
+    let err = block_on(base.get("rec1")).unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<airtable::Error>(),
+        Some(&airtable::Error::ResponseTooLarge { limit: 10 })
+    );
+    assert_eq!(client.sent.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
+
+// Notes when each request was sent, to check their pacing.
+#[derive(Debug, Clone, Default)]
+struct StampingClient {
+    inner: FakeClient,
+    sent: std::sync::Arc<std::sync::Mutex<Vec<std::time::Instant>>>,
+}
+
+impl airtable::http::HttpClient for StampingClient {
+    fn send(&self, request: airtable::http::Request) -> anyhow::Result<airtable::http::Response> {
+        self.sent.lock().unwrap().push(std::time::Instant::now());
+        self.inner.send(request)
+    }
+}
+
+// Fails if any one-second window holds more than `rate` of the `sent` times.
+fn assert_at_most_per_second(sent: &[std::time::Instant], rate: usize) {
+    for (i, start) in sent.iter().enumerate() {
+        let within = sent[i..]
+            .iter()
+            .filter(|at| at.duration_since(*start) < std::time::Duration::from_secs(1))
+            .count();
+        assert!(
+            within <= rate,
+            "{} requests within a second of request {}",
+            within,
+            i + 1
+        );
+    }
+}
+
+#[test]
+fn draining_a_query_never_outpaces_the_rate_limit() {
+    let client = StampingClient::default();
+    for i in 1..=7 {
+        let mut page =
+            json!({ "records": [{ "id": format!("rec{}", i), "fields": { "Word": "w" } }] });
+        if i < 7 {
+            page["offset"] = json!(format!("itr{}", i + 1));
+        }
+        client.inner.respond(200, &page.to_string());
+    }
+    let base = airtable::new::<Word>("key123", "app123", "Words")
+        .http_client(client.clone())
+        .with_rate_limit(4.0);
+
+    let words: Vec<Word> = base.query().into_iter().collect();
+
+    assert_eq!(words.len(), 7);
+    let sent = client.sent.lock().unwrap();
+    assert_eq!(sent.len(), 7);
+    assert_at_most_per_second(&sent, 4);
+}
+
+#[test]
+fn adaptive_page_sizes_grow_and_stay_within_the_rate_limit() {
+    let client = FakeClient::new();
+    let page = |i: usize| {
+        let mut page =
+            json!({ "records": [{ "id": format!("rec{}", i), "fields": { "Word": "w" } }] });
+        if i < 4 {
+            page["offset"] = json!(format!("itr{}", i + 1));
+        }
+        page.to_string()
+    };
+    for i in 1..=4 {
+        client.respond(200, &page(i));
+    }
+    let base = base(&client).with_rate_limit(2.0);
+
+    let start = std::time::Instant::now();
+    let words = base.query().adaptive_page_size().collect_all().unwrap();
+
+    assert_eq!(words.len(), 4);
+    let sizes: Vec<_> = client
+        .requests()
+        .iter()
+        .map(|request| query(request, "pageSize")[0].to_owned())
+        .collect();
+    // Quick pages double the size until the limiter runs dry, then pages are as big as they get.
+    assert_eq!(sizes, ["25", "50", "100", "100"]);
+    // Two go out at once, then two more once a second has passed.
+    assert!(start.elapsed() >= std::time::Duration::from_millis(900));
+    assert!(base.rate_limit_state().unwrap().requests_last_second <= 2);
+}
+
+#[test]
+fn ping_tells_bad_keys_from_missing_tables() {
+    let client = FakeClient::new();
+    client
+        .respond(200, r#"{ "records": [] }"#)
+        .respond(401, r#"{ "error": { "type": "AUTHENTICATION_REQUIRED" } }"#)
+        .respond(404, include_str!("fixtures/error_not_found.json"));
+    let base = base(&client);
+
+    block_on(base.ping()).unwrap();
+    assert_eq!(query(&client.requests()[0], "maxRecords"), ["1"]);
+
+    let err = block_on(base.ping()).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<airtable::Error>(),
+        Some(&airtable::Error::Unauthorized)
+    );
+
+    let err = block_on(base.ping()).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<airtable::Error>(),
+        Some(&airtable::Error::TableNotFound {
+            table: "Words".to_owned()
+        })
+    );
+}
+
+#[test]
+fn reads_and_writes_use_their_own_timeouts() {
+    let client = FakeClient::new();
+    client.respond(200, RECORD).respond(200, RECORD);
+    let base = base(&client)
+        .timeout(std::time::Duration::from_secs(10))
+        .read_timeout(std::time::Duration::from_secs(60));
+
+    block_on(base.get("rec1")).unwrap();
+    block_on(base.update(&lurid())).unwrap();
+
+    let requests = client.requests();
+    assert_eq!(
+        requests[0].timeout,
+        Some(std::time::Duration::from_secs(60))
+    );
+    assert_eq!(
+        requests[1].timeout,
+        Some(std::time::Duration::from_secs(10))
+    );
+}
+
+fn quick_retries() -> airtable::RetryPolicy {
+    airtable::RetryPolicy {
+        initial_backoff: std::time::Duration::from_millis(1),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn rate_limited_requests_are_retried() {
+    let client = FakeClient::new();
+    client.respond(429, "{}").respond(200, RECORD);
+
+    let base = base(&client).retry_policy(quick_retries());
+    let timed = block_on(base.create_timed(&lurid())).unwrap();
+
+    assert_eq!(timed.attempts, 2);
+    assert_eq!(client.requests().len(), 2);
+}
+
+#[test]
+fn retries_stop_once_the_duration_is_spent() {
+    let client = FakeClient::new();
+    for _ in 0..10 {
+        client.respond(429, "{}");
+    }
+
+    let policy = airtable::RetryPolicy {
+        max_attempts: 10,
+        initial_backoff: std::time::Duration::from_millis(50),
+        max_retry_duration: Some(std::time::Duration::from_millis(120)),
+        ..Default::default()
+    };
+    let base = base(&client).retry_policy(policy);
+
+    assert!(block_on(base.create(&lurid())).is_err());
+    // Waits 50ms, then stops rather than wait another 100ms.
+    assert_eq!(client.requests().len(), 2);
+}
+
+#[test]
+fn creates_are_not_retried_after_a_timeout_by_default() {
+    let client = FakeClient::new();
+    client.fail("timed out");
+
+    let base = base(&client).retry_policy(quick_retries());
+    assert!(block_on(base.create(&lurid())).is_err());
+    assert_eq!(client.requests().len(), 1);
+}
+
+#[test]
+fn creates_can_opt_in_to_retries_after_a_timeout() {
+    let client = FakeClient::new();
+    client.fail("timed out").respond(200, RECORD);
+
+    let policy = airtable::RetryPolicy {
+        retry_creates: true,
+        ..quick_retries()
+    };
+    let base = base(&client).retry_policy(policy);
+    let timed = block_on(base.create_timed(&lurid())).unwrap();
+    assert_eq!(timed.attempts, 2);
+}
+
+#[test]
+fn updates_are_retried_after_a_timeout() {
+    let client = FakeClient::new();
+    client.fail("timed out").respond(200, RECORD);
+
+    let base = base(&client).retry_policy(quick_retries());
+    let timed = block_on(base.update_timed(&lurid())).unwrap();
+    assert_eq!(timed.attempts, 2);
+}
+
+#[derive(Debug)]
+struct RetryServerErrors;
+
+impl airtable::RetryDecider for RetryServerErrors {
+    fn should_retry(&self, attempt: &airtable::Attempt<'_>) -> Option<std::time::Duration> {
+        let status = attempt.response?.status;
+        Some(std::time::Duration::from_millis(1)).filter(|_| status >= 500 && attempt.number < 3)
+    }
+}
+
+#[test]
+fn custom_retry_deciders_choose_what_to_retry() {
+    let client = FakeClient::new();
+    client.respond(503, "{}").respond(200, RECORD);
+
+    let base = base(&client).retry_decider(RetryServerErrors);
+    let timed = block_on(base.update_timed(&lurid())).unwrap();
+    assert_eq!(timed.attempts, 2);
+
+    client.respond(429, "{}");
+    assert!(block_on(base.update(&lurid())).is_err());
+    assert_eq!(client.requests().len(), 3);
+}
+
+#[test]
+fn collect_all_surfaces_a_failing_page() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1).respond(422, ERROR);
+
+    let base = base(&client);
+    assert!(base.query().collect_all().is_err());
+    assert_eq!(client.requests().len(), 2);
+}
+
+#[test]
+fn pages_yields_each_page_whole() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1).respond(200, PAGE_2);
+
+    let base = base(&client);
+    let pages: Vec<Vec<Word>> = base.query().pages().map(Result::unwrap).collect();
+
+    let sizes: Vec<usize> = pages.iter().map(Vec::len).collect();
+    assert_eq!(sizes, [2, 1]);
+    assert_eq!(client.requests().len(), 2);
+}
+
+#[test]
+fn collect_up_to_reports_more_records() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_1);
+
+    let (words, more) = base(&client).query().collect_up_to(1).unwrap();
+
+    assert_eq!(words.len(), 1);
+    assert!(more);
+    assert_eq!(query(&client.requests()[0], "maxRecords"), ["2"]);
+}
+
+#[test]
+fn collect_up_to_reports_no_more_records() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_2);
+
+    let (words, more) = base(&client).query().collect_up_to(2).unwrap();
+
+    assert_eq!(words.len(), 1);
+    assert!(!more);
+}
+
+#[test]
+fn collect_up_to_accepts_the_largest_limit() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_2);
+
+    let (words, more) = base(&client).query().collect_up_to(usize::MAX).unwrap();
+
+    assert_eq!(words.len(), 1);
+    assert!(!more);
+    assert_eq!(
+        query(&client.requests()[0], "maxRecords"),
+        [usize::MAX.to_string()]
+    );
+}
+
+fn known_to_google(word: &Word) -> anyhow::Result<()> {
+    if word.google == 0 {
+        anyhow::bail!("{} has no Google count", word.word);
+    }
+    Ok(())
+}
+
+#[test]
+fn records_failing_validation_fail_the_query() {
+    let client = FakeClient::new();
+    client.respond(200, include_str!("fixtures/unvalidated.json"));
+
+    let err = base(&client)
+        .query()
+        .validate(known_to_google)
+        .collect_all()
+        .unwrap_err();
+    assert!(err.to_string().contains("rec2"));
+}
+
+#[test]
+fn records_failing_validation_can_be_skipped() {
+    let client = FakeClient::new();
+    client.respond(200, include_str!("fixtures/unvalidated.json"));
+
+    let (words, invalid) = base(&client)
+        .query()
+        .validate(known_to_google)
+        .collect_valid()
+        .unwrap();
+    assert_eq!(words, [lurid()]);
+    assert_eq!(invalid[0].id, "rec2");
+    assert_eq!(invalid[0].error, "limpid has no Google count");
+}
+
+#[test]
+fn unknown_fields_can_fail_the_query() {
+    let client = FakeClient::new();
+    let page = json!({
+        "records": [{
+            "id": "rec1",
+            "fields": { "Word": "lurid", "Google": 6870000, "Definition": "vivid" }
+        }]
+    });
+    client
+        .respond(200, &page.to_string())
+        .respond(200, &page.to_string());
+    let base = base(&client);
+
+    let words = base
+        .query()
+        .unknown_fields(airtable::UnknownFields::Warn)
+        .collect_all()
+        .unwrap();
+    assert_eq!(words, [lurid()]);
+
+    let err = base
+        .query()
+        .unknown_fields(airtable::UnknownFields::Error)
+        .collect_all()
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<airtable::Error>(),
+        Some(&airtable::Error::UnknownFields {
+            id: "rec1".to_owned(),
+            fields: vec!["Definition".to_owned()],
+        })
+    );
+}
+
+#[test]
+fn formulas_accumulate_into_nested_ands_and_ors() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_2).respond(200, PAGE_2);
+    let base = base(&client);
+
+    let min_google = Some(1000000);
+    let level: Option<&str> = None;
+    let mut words = base.query();
+    words = words.and_formula("{Google} > 0");
+    if let Some(min) = min_google {
+        words = words.and_formula(format!("{{Google}} >= {}", min));
+    }
+    if let Some(level) = level {
+        words = words.and_formula(format!("{{Level}} = '{}'", level));
+    }
+    words = words.and_formula("OR({Word} = 'a', {Word} = 'b')");
+    words.collect_all().unwrap();
+
+    base.query()
+        .formula("{Google} > 0")
+        .or_formula("{Word} = 'lurid'")
+        .and_formula("NOT({Archived})")
+        .collect_all()
+        .unwrap();
+
+    let requests = client.requests();
+    assert_eq!(
+        query(&requests[0], "filterByFormula"),
+        ["AND(AND({Google} > 0, {Google} >= 1000000), OR({Word} = 'a', {Word} = 'b'))"]
+    );
+    assert_eq!(
+        query(&requests[1], "filterByFormula"),
+        ["AND(OR({Google} > 0, {Word} = 'lurid'), NOT({Archived}))"]
+    );
+}
+
+#[test]
+fn view_and_formula_are_sent_together() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_2);
+
+    let base = base(&client);
+    let words: Vec<Word> = base
+        .query()
+        .view("To Learn")
+        .formula("{Google} > 1000000")
+        .into_iter()
+        .collect();
+    assert_eq!(words.len(), 1);
+
+    let request = &client.requests()[0];
+    assert_eq!(query(request, "view"), ["To Learn"]);
+    assert_eq!(query(request, "filterByFormula"), ["{Google} > 1000000"]);
+}
+
+#[test]
+fn sort_by_created_sorts_on_the_given_field() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_2);
+
+    base(&client)
+        .query()
+        .sort_by_created("Added on", airtable::SortDirection::Ascending)
+        .collect_all()
+        .unwrap();
+
+    let request = &client.requests()[0];
+    assert_eq!(query(request, "sort[0][field]"), ["Added on"]);
+    assert_eq!(query(request, "sort[0][direction]"), ["asc"]);
+    assert!(query(request, "sort[1][field]").is_empty());
+}
+
+#[test]
+fn formula_args_that_cannot_bind_fail_before_sending() {
+    let client = FakeClient::new();
+
+    let err = base(&client)
+        .query()
+        .formula_args("{Google} > ?", &[f64::NAN.into()])
+        .collect_all()
+        .unwrap_err();
+
+    assert!(matches!(
+        err.downcast_ref::<airtable::Error>(),
+        Some(airtable::Error::InvalidFormula { .. })
+    ));
+    assert!(client.requests().is_empty());
+}
+
+#[test]
+fn newest_first_sorts_by_created_descending() {
+    let client = FakeClient::new();
+    client.respond(
+        200,
+        r#"{ "records": [
+            { "id": "rec2", "createdTime": "2021-04-01T12:01:00.000Z", "fields": { "Word": "sanguine" } },
+            { "id": "rec1", "createdTime": "2021-04-01T12:00:00.000Z", "fields": { "Word": "lurid" } }
+        ] }"#,
+    );
+    let base = airtable::new::<Annotated>("key123", "app123", "Words").http_client(client.clone());
+
+    let words = base
+        .query()
+        .newest_first()
+        .max_records(2)
+        .collect_all()
+        .unwrap();
+
+    // Airtable does the sorting; the crate has to ask for it and keep the order it gets.
+    let requests = client.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(query(&requests[0], "sort[0][field]"), ["Created"]);
+    assert_eq!(query(&requests[0], "sort[0][direction]"), ["desc"]);
+    assert_eq!(query(&requests[0], "maxRecords"), ["2"]);
+    let created: Vec<&str> = words
+        .iter()
+        .map(|word| word.created_time.as_deref().unwrap())
+        .collect();
+    assert_eq!(
+        created,
+        ["2021-04-01T12:01:00.000Z", "2021-04-01T12:00:00.000Z"]
+    );
+
+    client.respond(200, r#"{ "records": [] }"#);
+    base.query().newest_first_by("Added").collect_all().unwrap();
+    let request = &client.requests()[1];
+    assert_eq!(query(request, "sort[0][field]"), ["Added"]);
+    assert_eq!(query(request, "sort[0][direction]"), ["desc"]);
+}
+
+#[test]
+fn fields_can_be_selected_by_id() {
+    let client = FakeClient::new();
+    client.respond(200, r#"{ "records": [] }"#);
+
+    let base = base(&client);
+    let words: Vec<Word> = base
+        .query()
+        .fields_by_id(&["fldWord", "fldGoogle"])
+        .return_fields_by_field_id()
+        .into_iter()
+        .collect();
+    assert!(words.is_empty());
+
+    let request = &client.requests()[0];
+    assert_eq!(query(request, "fields[]"), ["fldWord", "fldGoogle"]);
+    assert_eq!(query(request, "returnFieldsByFieldId"), ["true"]);
+}
+
+#[test]
+fn a_complex_query_sends_every_param() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_2);
+
+    base(&client)
+        .query()
+        .view("To Learn")
+        .formula("{Google} > 1000000")
+        .sort("Google", airtable::SortDirection::Descending)
+        .sort("Word", airtable::SortDirection::Ascending)
+        .page_size(50)
+        .max_records(120)
+        .fields(&["Word", "Google"])
+        .collect_all()
+        .unwrap();
+
+    let requests = client.requests();
+    let pairs: Vec<(&str, &str)> = requests[0]
+        .query
+        .iter()
+        .map(|(param, value)| (param.as_str(), value.as_str()))
+        .collect();
+    assert_eq!(
+        pairs,
+        [
+            ("offset", ""),
+            ("view", "To Learn"),
+            ("filterByFormula", "{Google} > 1000000"),
+            ("sort[0][field]", "Google"),
+            ("sort[0][direction]", "desc"),
+            ("sort[1][field]", "Word"),
+            ("sort[1][direction]", "asc"),
+            ("pageSize", "50"),
+            ("maxRecords", "120"),
+            ("fields[]", "Word"),
+            ("fields[]", "Google"),
+        ]
+    );
+}
+
+#[test]
+fn cloned_query_templates_are_specialized_independently() {
+    use airtable::SortDirection;
+
+    let client = FakeClient::new();
+    client.respond(200, PAGE_2).respond(200, PAGE_2);
+
+    let base = base(&client);
+    let active = base.query().view("Active").formula("{Google} > 0");
+    active
+        .clone()
+        .sort("Word", SortDirection::Ascending)
+        .collect_all()
+        .unwrap();
+    active
+        .clone()
+        .and_formula("{Word} != ''")
+        .max_records(5)
+        .collect_all()
+        .unwrap();
+
+    let requests = client.requests();
+    assert_eq!(query(&requests[0], "view"), ["Active"]);
+    assert_eq!(query(&requests[0], "filterByFormula"), ["{Google} > 0"]);
+    assert_eq!(query(&requests[0], "sort[0][field]"), ["Word"]);
+    assert!(query(&requests[0], "maxRecords").is_empty());
+
+    assert_eq!(query(&requests[1], "view"), ["Active"]);
+    assert_eq!(
+        query(&requests[1], "filterByFormula"),
+        ["AND({Google} > 0, {Word} != '')"]
+    );
+    assert!(query(&requests[1], "sort[0][field]").is_empty());
+    assert_eq!(query(&requests[1], "maxRecords"), ["5"]);
+}
+
+#[test]
+fn cleared_view_and_sort_are_not_sent() {
+    let client = FakeClient::new();
+    client.respond(200, PAGE_2);
+
+    let base = base(&client);
+    let template = base
+        .query()
+        .view("To Learn")
+        .sort("Word", airtable::SortDirection::Ascending);
+    let words: Vec<Word> = template
+        .clone()
+        .view_clear()
+        .sort_clear()
+        .into_iter()
+        .collect();
+    assert_eq!(words.len(), 1);
+
+    let request = &client.requests()[0];
+    assert!(query(request, "view").is_empty());
+    assert!(request
+        .query
+        .iter()
+        .all(|(key, _)| !key.starts_with("sort")));
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, PartialEq)]
+struct Contact {
+    #[serde(default, skip_serializing)]
+    id: airtable::RecordId,
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(flatten)]
+    address: Address,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, PartialEq)]
+struct Address {
+    #[serde(rename = "Street")]
+    street: String,
+    #[serde(rename = "City")]
+    city: String,
+}
+
+airtable::impl_record!(Contact);
+
+#[test]
+fn flattened_fields_stay_inside_the_fields_envelope() {
+    let client = FakeClient::new();
+    let record = json!({
+        "id": "rec7",
+        "createdTime": "2021-04-01T12:00:00.000Z",
+        "fields": { "Name": "Ada", "Street": "12 Analytical Row", "City": "London" }
+    });
+    client.respond(200, &record.to_string());
+    client.respond(200, &json!({ "records": [record] }).to_string());
+
+    let base = airtable::new::<Contact>("key123", "app123", "Contacts").http_client(client.clone());
+    let contact = Contact {
+        id: Default::default(),
+        name: "Ada".to_owned(),
+        address: Address {
+            street: "12 Analytical Row".to_owned(),
+            city: "London".to_owned(),
+        },
+    };
+    block_on(base.create(&contact)).unwrap();
+
+    let body: serde_json::Value =
+        serde_json::from_str(client.requests()[0].body.as_ref().unwrap()).unwrap();
+    assert_eq!(
+        body,
+        json!({ "fields": { "Name": "Ada", "Street": "12 Analytical Row", "City": "London" } })
+    );
+
+    let read: Vec<Contact> = base.query().into_iter().collect();
+    assert_eq!(read[0].id, "rec7");
+    assert_eq!(read[0].address, contact.address);
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct Entry {
+    #[serde(default)]
+    id: airtable::RecordId,
+    #[serde(rename = "Word", default)]
+    word: airtable::fields::Selected<String>,
+    #[serde(rename = "Notes", default)]
+    notes: airtable::fields::Selected<String>,
+    #[serde(rename = "Google", default)]
+    google: airtable::fields::Selected<i64>,
+}
+
+airtable::impl_record!(Entry);
+
+#[test]
+fn selected_fields_tell_empty_from_not_selected() {
+    use airtable::fields::Selected;
+
+    let client = FakeClient::new();
+    let page = json!({ "records": [{ "id": "rec3", "fields": { "Word": "obdurate" } }] });
+    client.respond(200, &page.to_string());
+    let base = airtable::new::<Entry>("key123", "app123", "Words").http_client(client.clone());
+
+    let entries = base
+        .query()
+        .fields(&["Word", "Notes"])
+        .empty_fields_as_null()
+        .collect_all()
+        .unwrap();
+
+    let entry = &entries[0];
+    assert_eq!(entry.word, Selected::Value("obdurate".to_owned()));
+    assert_eq!(entry.notes, Selected::Empty);
+    assert_eq!(entry.google, Selected::NotSelected);
+    assert_eq!(query(&client.requests()[0], "fields[]"), ["Word", "Notes"]);
+}
+
+#[test]
+fn raw_requests_are_sent_with_auth_to_the_api_root() {
+    let client = FakeClient::new();
+    client
+        .respond(200, r#"{ "id": "tblNew", "name": "Synonyms" }"#)
+        .respond(200, "");
+
+    let base = base(&client).endpoints(airtable::Endpoints::with_root("http://mock/v0/"));
+    let table = block_on(base.request(
+        "post",
+        "/meta/bases/app123/tables",
+        Some(&json!({ "name": "Synonyms" })),
+    ))
+    .unwrap();
+    let nothing = block_on(base.request("DELETE", "app123/Words/rec1", None)).unwrap();
+
+    assert_eq!(table, json!({ "id": "tblNew", "name": "Synonyms" }));
+    assert_eq!(nothing, serde_json::Value::Null);
+    let requests = client.requests();
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].url, "http://mock/v0/meta/bases/app123/tables");
+    assert_eq!(header(&requests[0], "Authorization"), Some("Bearer key123"));
+    assert_eq!(requests[0].body.as_deref(), Some(r#"{"name":"Synonyms"}"#));
+    assert_eq!(requests[1].method, "DELETE");
+    assert_eq!(requests[1].url, "http://mock/v0/app123/Words/rec1");
+    assert_eq!(requests[1].body, None);
+}
+
+#[test]
+fn raw_requests_reject_unknown_methods_before_sending() {
+    let client = FakeClient::new();
+
+    let err = block_on(base(&client).request("FETCH", "/meta/bases", None)).unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<airtable::Error>(),
+        Some(&airtable::Error::InvalidMethod {
+            method: "FETCH".to_owned()
+        })
+    );
+    assert!(client.requests().is_empty());
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, PartialEq)]
+struct Annotated {
+    #[serde(default, skip_serializing)]
+    id: airtable::RecordId,
+    #[serde(rename = "Word")]
+    word: String,
+    #[serde(skip)]
+    created_time: Option<String>,
+    #[serde(skip)]
+    comments: Option<u64>,
+}
+
+impl airtable::Record for Annotated {
+    fn set_id(&mut self, id: airtable::RecordId) {
+        self.id = id;
+    }
+
+    fn id(&self) -> &airtable::RecordId {
+        &self.id
+    }
+
+    fn set_created_time(&mut self, created_time: String) {
+        self.created_time = Some(created_time);
+    }
+
+    fn set_comment_count(&mut self, comment_count: u64) {
+        self.comments = Some(comment_count);
+    }
+}
+
+#[test]
+fn comment_counts_are_asked_for_and_read() {
+    let client = FakeClient::new();
+    client.respond(
+        200,
+        r#"{ "records": [
+            { "id": "rec1", "commentCount": 3, "fields": { "Word": "lurid" } },
+            { "id": "rec2", "fields": { "Word": "sanguine" } }
+        ] }"#,
+    );
+
+    let base = airtable::new::<Annotated>("key123", "app123", "Words").http_client(client.clone());
+    let words = base.query().comment_count().collect_all().unwrap();
+
+    assert_eq!(
+        query(&client.requests()[0], "recordMetadata[]"),
+        ["commentCount"]
+    );
+    assert_eq!(words[0].comments, Some(3));
+    assert_eq!(words[1].comments, None);
+}
+
+#[test]
+fn records_keep_their_id_and_metadata_through_a_read_and_write() {
+    let client = FakeClient::new();
+    client.respond(200, RECORD).respond(200, RECORD);
+    let base = airtable::new::<Annotated>("key123", "app123", "Words").http_client(client.clone());
+
+    let mut word = block_on(base.get("rec1")).unwrap();
+    assert_eq!(word.id, "rec1");
+    assert_eq!(
+        word.created_time.as_deref(),
+        Some("2021-04-01T12:00:00.000Z")
+    );
+    assert_eq!(word.word, "lurid");
+
+    word.word = "lurider".to_owned();
+    let updated = block_on(base.update_by_id(&word.id.clone(), &word)).unwrap();
+
+    // Writing sends the fields alone; the id goes in the url and the metadata is left out.
+    let request = &client.requests()[1];
+    assert_eq!(request.url, "https://api.airtable.com/v0/app123/Words/rec1");
+    let body: serde_json::Value = serde_json::from_str(request.body.as_ref().unwrap()).unwrap();
+    assert_eq!(body, json!({ "fields": { "Word": "lurider" } }));
+    assert_eq!(updated.id, "rec1");
+    assert_eq!(
+        updated.created_time.as_deref(),
+        Some("2021-04-01T12:00:00.000Z")
+    );
+}
@@ -0,0 +1,266 @@
+mod common;
+
+use airtable::metadata::{Choice, FieldKind};
+use common::{base, block_on, query, FakeClient};
+
+#[test]
+fn field_options_are_typed_by_field_type() {
+    let client = FakeClient::new();
+    client.respond(200, include_str!("fixtures/tables.json"));
+
+    let schema = block_on(base(&client).schema()).unwrap();
+    let kind = |name| schema.field(name).unwrap().kind();
+
+    assert_eq!(
+        kind("Word"),
+        FieldKind::Other {
+            field_type: "singleLineText".to_owned()
+        }
+    );
+    match kind("Level") {
+        FieldKind::SingleSelect { choices } => {
+            let names: Vec<&str> = choices.iter().map(|c| c.name.as_str()).collect();
+            assert_eq!(names, ["Easy", "Hard"]);
+            assert_eq!(choices[0].color.as_deref(), Some("greenLight2"));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(
+        kind("Tags"),
+        FieldKind::MultipleSelects {
+            choices: vec![Choice {
+                id: "selNoun".to_owned(),
+                name: "Noun".to_owned(),
+                color: None,
+            }]
+        }
+    );
+    match kind("Lists") {
+        FieldKind::Link(link) => {
+            assert_eq!(link.linked_table_id, "tblLists");
+            assert_eq!(link.inverse_link_field_id.as_deref(), Some("fldWords"));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(
+        kind("Score"),
+        FieldKind::Formula {
+            result: Some(Box::new(FieldKind::Other {
+                field_type: "number".to_owned()
+            }))
+        }
+    );
+    assert_eq!(
+        kind("List names"),
+        FieldKind::Lookup {
+            result: Some(Box::new(FieldKind::Other {
+                field_type: "singleLineText".to_owned()
+            }))
+        }
+    );
+}
+
+#[test]
+fn records_are_validated_against_the_schema() {
+    use airtable::metadata::ValidationIssue;
+    use serde_json::json;
+
+    let client = FakeClient::new();
+    client.respond(200, include_str!("fixtures/tables.json"));
+    let schema = block_on(base(&client).schema()).unwrap();
+
+    let valid = json!({ "Word": "lurid", "Level": "Hard", "Tags": ["Noun"], "Google": 6870000 });
+    assert_eq!(schema.validate_record(&valid), []);
+
+    let invalid = json!({
+        "Level": "Impossible",
+        "Tags": "Noun",
+        "Google": "lots",
+        "Score": 3,
+        "Definition": "vivid"
+    });
+    let issues = schema.validate_record_requiring(&invalid, &["Word"]);
+    for issue in [
+        ValidationIssue::InvalidOption {
+            field: "Level".to_owned(),
+            option: "Impossible".to_owned(),
+        },
+        ValidationIssue::WrongType {
+            field: "Tags".to_owned(),
+            expected: "array",
+        },
+        ValidationIssue::WrongType {
+            field: "Google".to_owned(),
+            expected: "number",
+        },
+        ValidationIssue::ReadOnlyField {
+            field: "Score".to_owned(),
+        },
+        ValidationIssue::UnknownField {
+            field: "Definition".to_owned(),
+        },
+        ValidationIssue::MissingField {
+            field: "Word".to_owned(),
+        },
+    ] {
+        assert!(issues.contains(&issue), "missing {:?}", issue);
+    }
+    assert_eq!(issues.len(), 6);
+}
+
+#[test]
+fn field_ids_remap_to_names() {
+    use airtable::metadata::remap_field_ids;
+    use serde_json::json;
+
+    let client = FakeClient::new();
+    client.respond(200, include_str!("fixtures/tables.json"));
+    let schema = block_on(base(&client).schema()).unwrap();
+
+    let page = json!({
+        "records": [{
+            "id": "rec1",
+            "createdTime": "2021-04-01T12:00:00.000Z",
+            "fields": { "fldWord": "lurid", "fldLevel": "Hard", "fldNew": 1 }
+        }],
+        "offset": "itrNext/rec1"
+    });
+
+    assert_eq!(
+        remap_field_ids(page, &schema),
+        json!({
+            "records": [{
+                "id": "rec1",
+                "createdTime": "2021-04-01T12:00:00.000Z",
+                "fields": { "Word": "lurid", "Level": "Hard", "fldNew": 1 }
+            }],
+            "offset": "itrNext/rec1"
+        })
+    );
+    assert_eq!(
+        remap_field_ids(json!({ "fldTags": ["Noun"] }), &schema),
+        json!({ "Tags": ["Noun"] })
+    );
+}
+
+#[test]
+fn field_map_pairs_ids_with_names() {
+    let client = FakeClient::new();
+    client.respond(200, include_str!("fixtures/tables.json"));
+    let base = base(&client).cache_schema(std::time::Duration::from_secs(60));
+
+    let by_id = block_on(base.field_map()).unwrap();
+    assert_eq!(by_id.len(), 7);
+    assert_eq!(by_id["fldWord"], "Word");
+    assert_eq!(by_id["fldListNames"], "List names");
+
+    let by_name = block_on(base.schema()).unwrap().field_ids_by_name();
+    assert_eq!(by_name["List names"], "fldListNames");
+    assert_eq!(block_on(base.field_map()).unwrap(), by_id);
+    assert_eq!(client.requests().len(), 1);
+}
+
+#[test]
+fn permission_levels_parse_and_order() {
+    use airtable::metadata::PermissionLevel;
+
+    let client = FakeClient::new();
+    client.respond(
+        200,
+        r#"{ "bases": [
+            { "id": "app1", "name": "Words", "permissionLevel": "none" },
+            { "id": "app2", "name": "Lists", "permissionLevel": "read" },
+            { "id": "app3", "name": "Notes", "permissionLevel": "comment" },
+            { "id": "app4", "name": "Tasks", "permissionLevel": "edit" },
+            { "id": "app5", "name": "Owned", "permissionLevel": "create" }
+        ] }"#,
+    );
+
+    let levels: Vec<_> = block_on(base(&client).list_bases())
+        .unwrap()
+        .into_iter()
+        .map(|info| info.permission_level)
+        .collect();
+    assert_eq!(
+        levels,
+        [
+            PermissionLevel::None,
+            PermissionLevel::Read,
+            PermissionLevel::Comment,
+            PermissionLevel::Edit,
+            PermissionLevel::Create
+        ]
+    );
+
+    assert!(PermissionLevel::Edit >= PermissionLevel::Read);
+    assert!(PermissionLevel::Comment < PermissionLevel::Edit);
+    for level in levels {
+        assert_eq!(level.to_string().parse::<PermissionLevel>().unwrap(), level);
+    }
+    assert!("owner".parse::<PermissionLevel>().is_err());
+}
+
+#[test]
+fn schema_hashes_ignore_order_but_not_changes() {
+    let client = FakeClient::new();
+    client
+        .respond(200, include_str!("fixtures/tables.json"))
+        .respond(200, include_str!("fixtures/tables.json"));
+    let hash = block_on(base(&client).schema_hash()).unwrap();
+    let schema = block_on(base(&client).schema()).unwrap();
+    assert_eq!(schema.hash(), hash);
+
+    let mut reordered = schema.clone();
+    reordered.fields.reverse();
+    let options = reordered
+        .fields
+        .iter_mut()
+        .find_map(|field| field.options.as_mut())
+        .unwrap();
+    let keys: serde_json::Map<_, _> = options
+        .as_object()
+        .unwrap()
+        .iter()
+        .rev()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    *options = serde_json::Value::Object(keys);
+    assert_eq!(reordered.hash(), hash);
+
+    let mut renamed = schema.clone();
+    renamed.fields[0].name = "Term".to_owned();
+    assert_ne!(renamed.hash(), hash);
+
+    let mut retyped = schema;
+    retyped.fields[0].field_type = "multilineText".to_owned();
+    assert_ne!(retyped.hash(), hash);
+}
+
+#[test]
+fn list_bases_follows_the_offset_across_pages() {
+    let client = FakeClient::new();
+    client
+        .respond(
+            200,
+            r#"{ "bases": [
+                { "id": "app1", "name": "Words", "permissionLevel": "create" },
+                { "id": "app2", "name": "Lists", "permissionLevel": "read" }
+            ], "offset": "itrBases/app2" }"#,
+        )
+        .respond(
+            200,
+            r#"{ "bases": [{ "id": "app3", "name": "Notes", "permissionLevel": "edit" }] }"#,
+        );
+
+    let bases = block_on(base(&client).list_bases()).unwrap();
+
+    let ids: Vec<&str> = bases.iter().map(|info| info.id.as_str()).collect();
+    assert_eq!(ids, ["app1", "app2", "app3"]);
+    let requests = client.requests();
+    assert_eq!(requests.len(), 2);
+    for request in &requests {
+        assert_eq!(request.url, "https://api.airtable.com/v0/meta/bases");
+    }
+    assert!(query(&requests[0], "offset").is_empty());
+    assert_eq!(query(&requests[1], "offset"), ["itrBases/app2"]);
+}
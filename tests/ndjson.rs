@@ -0,0 +1,80 @@
+mod common;
+
+use common::{base, block_on, query, FakeClient};
+
+#[test]
+fn export_writes_one_line_per_record_across_pages() {
+    let client = FakeClient::new();
+    client
+        .respond(200, include_str!("fixtures/page_1.json"))
+        .respond(200, include_str!("fixtures/page_2.json"));
+
+    let mut out = vec![];
+    let written = block_on(base(&client).export_ndjson(&mut out)).unwrap();
+
+    assert_eq!(written, 3);
+    let lines: Vec<serde_json::Value> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["id"], "rec1");
+    assert_eq!(lines[0]["createdTime"], "2021-04-01T12:00:00.000Z");
+    assert_eq!(lines[2]["fields"]["Word"], "obdurate");
+    assert_eq!(query(&client.requests()[1], "offset"), ["itrNext/rec2"]);
+}
+
+#[test]
+fn export_keeps_earlier_pages_when_a_later_one_fails() {
+    let client = FakeClient::new();
+    client
+        .respond(200, include_str!("fixtures/page_1.json"))
+        .respond(422, include_str!("fixtures/error_invalid_value.json"));
+
+    let mut out = vec![];
+    let base = base(&client).retry_policy(airtable::RetryPolicy::none());
+    assert!(block_on(base.export_ndjson(&mut out)).is_err());
+
+    assert_eq!(String::from_utf8(out).unwrap().lines().count(), 2);
+}
+
+#[test]
+fn import_creates_ten_records_per_request() {
+    let client = FakeClient::new();
+    let created = |count: usize| {
+        let records: Vec<_> = (0..count)
+            .map(|i| serde_json::json!({ "id": format!("rec{}", i), "fields": {} }))
+            .collect();
+        serde_json::json!({ "records": records }).to_string()
+    };
+    client.respond(200, &created(10)).respond(200, &created(2));
+
+    let base = base(&client).typecast(true).batch_concurrency(1);
+    let imported =
+        block_on(base.import_records(include_str!("fixtures/import.ndjson").as_bytes())).unwrap();
+
+    assert_eq!(imported, 12);
+    let requests = client.requests();
+    assert_eq!(requests.len(), 2);
+    let body: serde_json::Value = serde_json::from_str(requests[1].body.as_ref().unwrap()).unwrap();
+    assert_eq!(body["typecast"], true);
+    assert_eq!(
+        body["records"],
+        serde_json::json!([
+            { "fields": { "Word": "turgid", "Google": "11000" } },
+            { "fields": { "Word": "avid", "Google": "12000" } },
+        ])
+    );
+}
+
+#[test]
+fn import_rejects_a_malformed_line_before_sending() {
+    let client = FakeClient::new();
+    let input = "{ \"Word\": \"lurid\" }\n{ \"Word\": \n";
+
+    let err = block_on(base(&client).import_records(input.as_bytes())).unwrap_err();
+
+    assert!(err.to_string().contains("line 2"), "{}", err);
+    assert!(client.requests().is_empty());
+}